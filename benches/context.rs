@@ -0,0 +1,76 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use thiserror::Error;
+use thiserror_context::{impl_context, Context};
+
+#[derive(Debug, Error)]
+pub enum BenchErrorInner {
+    #[error("bench err")]
+    Bench,
+}
+impl_context!(BenchError(BenchErrorInner));
+
+const DEPTHS: [usize; 3] = [1, 10, 100];
+
+fn with_frames(depth: usize) -> BenchError {
+    let mut r: Result<(), BenchError> = Err(BenchErrorInner::Bench.into());
+    for _ in 0..depth {
+        r = r.context("retrying");
+    }
+    r.unwrap_err()
+}
+
+fn add_context(c: &mut Criterion) {
+    let mut group = c.benchmark_group("add_context");
+    for depth in DEPTHS {
+        group.bench_with_input(BenchmarkId::from_parameter(depth), &depth, |b, &depth| {
+            b.iter(|| with_frames(black_box(depth)))
+        });
+    }
+    group.finish();
+}
+
+fn into_inner(c: &mut Criterion) {
+    let mut group = c.benchmark_group("into_inner");
+    for depth in DEPTHS {
+        group.bench_with_input(BenchmarkId::from_parameter(depth), &depth, |b, &depth| {
+            b.iter_batched(
+                || with_frames(depth),
+                |e| e.into_inner(),
+                criterion::BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn debug_report(c: &mut Criterion) {
+    let mut group = c.benchmark_group("debug_report");
+    for depth in DEPTHS {
+        let e = with_frames(depth);
+        let mut buf = String::with_capacity(64 * 1024);
+        group.bench_with_input(BenchmarkId::from_parameter(depth), &e, |b, e| {
+            b.iter(|| {
+                use std::fmt::Write;
+                buf.clear();
+                write!(buf, "{:#?}", e).unwrap();
+            })
+        });
+    }
+    group.finish();
+}
+
+fn source_chain(c: &mut Criterion) {
+    use std::error::Error;
+
+    let mut group = c.benchmark_group("source_chain");
+    for depth in DEPTHS {
+        let e = with_frames(depth);
+        group.bench_with_input(BenchmarkId::from_parameter(depth), &e, |b, e| {
+            b.iter(|| std::iter::successors(e.source(), |&e| e.source()).count())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, add_context, into_inner, debug_report, source_chain);
+criterion_main!(benches);
@@ -0,0 +1,352 @@
+//! The `ErrorContext` derive macro of
+//! [thiserror-context](https://docs.rs/thiserror-context).
+//!
+//! This crate is not meant to be used directly. Enable the `derive` feature of
+//! `thiserror-context` and use `thiserror_context::ErrorContext` instead.
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::spanned::Spanned;
+use syn::{parse_macro_input, Attribute, Data, DeriveInput, Ident, LitStr, Path, Visibility};
+
+/// Defines a context enriched wrapper around the annotated error type, like
+/// `impl_context!` does.
+///
+/// The wrapper is configured through the `#[context(...)]` attribute:
+///
+/// - `wrapper = "Name"`: the name of the wrapper type. Required.
+/// - `vis = "pub(crate)"`: the visibility of the wrapper. Defaults to the
+///   visibility of the error type.
+/// - `doc = "..."`: documentation for the wrapper. Can be repeated. Defaults
+///   to the documentation of the error type.
+/// - `attr(...)`: any other attribute to put on the wrapper. Can be repeated.
+/// - `crate = "path"`: the path to `thiserror_context`, if it was renamed or
+///   re-exported.
+///
+/// `#[cfg(...)]` attributes of the error type are applied to the wrapper as
+/// well.
+///
+/// The wrapper has the same generic parameters and where-clause as the error
+/// type. Const generics are not supported.
+///
+/// ** Example **
+/// ```ignore
+/// use thiserror::Error;
+/// use thiserror_context::ErrorContext;
+///
+/// /// Errors of the users module.
+/// #[derive(Debug, Error, ErrorContext)]
+/// #[context(wrapper = "UserError", vis = "pub(crate)")]
+/// pub enum UserErrorInner {
+///     #[error("user not found")]
+///     NotFound,
+/// }
+/// ```
+#[proc_macro_derive(ErrorContext, attributes(context))]
+pub fn derive_error_context(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// The options given through `#[context(...)]`.
+struct Options {
+    wrapper: Ident,
+    vis: Option<Visibility>,
+    docs: Vec<LitStr>,
+    attrs: Vec<TokenStream>,
+    krate: Path,
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream> {
+    if let Data::Union(data) = &input.data {
+        return Err(syn::Error::new(
+            data.union_token.span,
+            "ErrorContext can't be derived for unions",
+        ));
+    }
+    if let Some(param) = input.generics.const_params().next() {
+        return Err(syn::Error::new(
+            param.span(),
+            "ErrorContext can't be derived for error types with const generics",
+        ));
+    }
+
+    let options = parse_options(&input)?;
+    let ty = &input.ident;
+    let wrapper = &options.wrapper;
+    let vis = options.vis.as_ref().unwrap_or(&input.vis);
+    let krate = &options.krate;
+    let attrs = &options.attrs;
+
+    // The wrapper only exists wherever the error type does.
+    let cfgs: Vec<&Attribute> = input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("cfg"))
+        .collect();
+
+    // Without explicit docs, the wrapper is documented like the error type.
+    let docs: Vec<TokenStream> = if options.docs.is_empty() {
+        input
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("doc"))
+            .map(|attr| quote!(#attr))
+            .collect()
+    } else {
+        options
+            .docs
+            .iter()
+            .map(|doc| quote!(#[doc = #doc]))
+            .collect()
+    };
+
+    // `__impl_context!` takes the bare generic parameters, so their inline
+    // bounds join the where-clause.
+    let generics = &input.generics;
+    let (_, ty_generics, where_clause) = generics.split_for_impl();
+    let lifetimes = generics.lifetimes().map(|param| &param.lifetime);
+    let params = generics.type_params().map(|param| &param.ident);
+    let bounds = generics
+        .lifetimes()
+        .filter(|param| !param.bounds.is_empty())
+        .map(|param| {
+            let (lifetime, bounds) = (&param.lifetime, &param.bounds);
+            quote!(#lifetime: #bounds)
+        })
+        .chain(
+            generics
+                .type_params()
+                .filter(|param| !param.bounds.is_empty())
+                .map(|param| {
+                    let (ident, bounds) = (&param.ident, &param.bounds);
+                    quote!(#ident: #bounds)
+                }),
+        )
+        .chain(
+            where_clause
+                .into_iter()
+                .flat_map(|clause| &clause.predicates)
+                .map(|predicate| quote!(#predicate)),
+        );
+
+    Ok(quote! {
+        #(#cfgs)*
+        #(#docs)*
+        #(#[#attrs])*
+        #vis struct #wrapper #generics (#krate::Contextual<#ty #ty_generics>) #where_clause;
+
+        #(#cfgs)*
+        #krate::__impl_context!(#wrapper[#(#lifetimes,)* #(#params,)*](#ty #ty_generics)[#(#bounds),*]);
+    })
+}
+
+fn parse_options(input: &DeriveInput) -> syn::Result<Options> {
+    let mut attrs = input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("context"));
+    let Some(attr) = attrs.next() else {
+        return Err(syn::Error::new(
+            input.ident.span(),
+            "missing `#[context(wrapper = \"...\")]` attribute",
+        ));
+    };
+    if let Some(duplicate) = attrs.next() {
+        return Err(syn::Error::new(
+            duplicate.span(),
+            "duplicate `#[context(...)]` attribute",
+        ));
+    }
+
+    let mut wrapper = None;
+    let mut vis = None;
+    let mut docs = Vec::new();
+    let mut extra = Vec::new();
+    let mut krate = None;
+    attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident("wrapper") {
+            set_once(&meta, &mut wrapper, |s| s.parse())
+        } else if meta.path.is_ident("vis") {
+            set_once(&meta, &mut vis, |s| s.parse())
+        } else if meta.path.is_ident("crate") {
+            set_once(&meta, &mut krate, |s| s.parse())
+        } else if meta.path.is_ident("doc") {
+            docs.push(meta.value()?.parse()?);
+            Ok(())
+        } else if meta.path.is_ident("attr") {
+            let content;
+            syn::parenthesized!(content in meta.input);
+            extra.push(content.parse()?);
+            Ok(())
+        } else {
+            Err(meta.error(
+                "unknown option, expected one of `wrapper`, `vis`, `doc`, `attr` or `crate`",
+            ))
+        }
+    })?;
+
+    let Some(wrapper) = wrapper else {
+        return Err(syn::Error::new(
+            attr.span(),
+            "missing `wrapper = \"...\"`, the name of the wrapper type",
+        ));
+    };
+    Ok(Options {
+        wrapper,
+        vis,
+        docs,
+        attrs: extra,
+        krate: krate.unwrap_or_else(|| syn::parse_quote!(::thiserror_context)),
+    })
+}
+
+/// Parses the string value of an option that can only be given once.
+fn set_once<T>(
+    meta: &syn::meta::ParseNestedMeta,
+    slot: &mut Option<T>,
+    parse: impl FnOnce(&LitStr) -> syn::Result<T>,
+) -> syn::Result<()> {
+    let name = meta.path.get_ident().unwrap();
+    if slot.is_some() {
+        return Err(meta.error(format_args!("duplicate `{name}` option")));
+    }
+    let value: LitStr = meta.value()?.parse()?;
+    let parsed = parse(&value).map_err(|_| {
+        syn::Error::new(
+            value.span(),
+            format_args!("invalid `{name}`: {:?}", value.value()),
+        )
+    })?;
+    *slot = Some(parsed);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    fn error(input: DeriveInput) -> String {
+        expand(input).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn expands_to_wrapper() {
+        let input: DeriveInput = parse_quote! {
+            /// An inner error.
+            #[cfg(unix)]
+            #[context(wrapper = "ThisError", vis = "pub(crate)", attr(must_use))]
+            pub enum ThisErrorInner {
+                Placeholder,
+            }
+        };
+        let expected = quote! {
+            #[cfg(unix)]
+            #[doc = r" An inner error."]
+            #[must_use]
+            pub(crate) struct ThisError(::thiserror_context::Contextual<ThisErrorInner>);
+
+            #[cfg(unix)]
+            ::thiserror_context::__impl_context!(ThisError[](ThisErrorInner)[]);
+        };
+        assert_eq!(expand(input).unwrap().to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn explicit_docs_and_crate() {
+        let input: DeriveInput = parse_quote! {
+            /// Not forwarded.
+            #[context(wrapper = "ThisError", doc = "The wrapper.", crate = "my::reexport")]
+            struct ThisErrorInner;
+        };
+        let expected = quote! {
+            #[doc = "The wrapper."]
+            struct ThisError(my::reexport::Contextual<ThisErrorInner>);
+
+            my::reexport::__impl_context!(ThisError[](ThisErrorInner)[]);
+        };
+        assert_eq!(expand(input).unwrap().to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn forwards_generics() {
+        let input: DeriveInput = parse_quote! {
+            #[context(wrapper = "StoreError")]
+            pub enum StoreErrorInner<'a: 'static, K: Debug, E = io::Error>
+            where
+                E: Error,
+            {
+                NotFound(&'a K),
+                Io(E),
+            }
+        };
+        let expected = quote! {
+            pub struct StoreError<'a: 'static, K: Debug, E = io::Error>(
+                ::thiserror_context::Contextual<StoreErrorInner<'a, K, E> >
+            )
+            where
+                E: Error,;
+
+            ::thiserror_context::__impl_context!(
+                StoreError['a, K, E,](StoreErrorInner<'a, K, E>)['a: 'static, K: Debug, E: Error]
+            );
+        };
+        assert_eq!(expand(input).unwrap().to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn misuse_is_reported() {
+        assert_eq!(
+            error(parse_quote!(
+                enum E {}
+            )),
+            "missing `#[context(wrapper = \"...\")]` attribute"
+        );
+        assert_eq!(
+            error(parse_quote!(
+                #[context(vis = "pub")]
+                enum E {}
+            )),
+            "missing `wrapper = \"...\"`, the name of the wrapper type"
+        );
+        assert_eq!(
+            error(parse_quote!(
+                #[context(wrapper = "Not An Ident")]
+                enum E {}
+            )),
+            "invalid `wrapper`: \"Not An Ident\""
+        );
+        assert_eq!(
+            error(parse_quote!(
+                #[context(wrapper = "W", wrapper = "V")]
+                enum E {}
+            )),
+            "duplicate `wrapper` option"
+        );
+        assert_eq!(
+            error(parse_quote!(
+                #[context(wrapper = "W", visibility = "pub")]
+                enum E {}
+            )),
+            "unknown option, expected one of `wrapper`, `vis`, `doc`, `attr` or `crate`"
+        );
+        assert_eq!(
+            error(parse_quote!(
+                #[context(wrapper = "W")]
+                struct E<const N: usize>([u8; N]);
+            )),
+            "ErrorContext can't be derived for error types with const generics"
+        );
+        assert_eq!(
+            error(parse_quote!(
+                #[context(wrapper = "W")]
+                union E {
+                    a: u8,
+                }
+            )),
+            "ErrorContext can't be derived for unions"
+        );
+    }
+}
@@ -0,0 +1,125 @@
+//! Conversions between [impl_context](crate::impl_context) wrappers and
+//! [anyhow::Error], behind the `anyhow` feature.
+//!
+//! `into_anyhow` re-applies every context frame as an anyhow context, so the
+//! resulting error reports the same way as one built with anyhow from the
+//! start. `try_from_anyhow` goes the other way, as long as the error was
+//! built on top of the wrapper's root error type, or of the wrapper itself.
+//!
+//! anyhow already converts any error into [anyhow::Error], wrappers included,
+//! so `?` keeps wrapping the whole wrapper. Its context frames remain visible
+//! through the source chain, but `into_anyhow` has to be called explicitly to
+//! turn them into anyhow contexts.
+//!
+//! Locations, fields, attachments and the backtrace are not carried over.
+//!
+//! ** Example **
+//! ```
+//! use thiserror::Error;
+//! use thiserror_context::{Context, impl_context};
+//!
+//! #[derive(Debug, Error)]
+//! enum ThisErrorInner {
+//!     #[error("placeholder err")]
+//!     Placeholder,
+//! }
+//! impl_context!(ThisError(ThisErrorInner));
+//!
+//! let err = Err::<(), _>(ThisErrorInner::Placeholder)
+//!     .context("inner")
+//!     .context("outer")
+//!     .unwrap_err();
+//!
+//! let err = err.into_anyhow();
+//! assert_eq!(format!("{:#}", err), "outer: inner: placeholder err");
+//!
+//! let err = ThisError::try_from_anyhow(err.context("handler")).unwrap();
+//! assert_eq!(err.contexts().collect::<Vec<_>>(), ["handler", "outer", "inner"]);
+//!
+//! assert!(ThisError::try_from_anyhow(anyhow::anyhow!("other")).is_err());
+//! ```
+use crate::contextual::{Contextual, Wrapper};
+use std::error::Error;
+
+#[doc(hidden)]
+pub use anyhow as __anyhow;
+
+impl<E: Error + Send + Sync + 'static> Contextual<E> {
+    /// Converts this error into an [anyhow::Error], re-applying every
+    /// context frame as an anyhow context.
+    pub fn into_anyhow(self) -> anyhow::Error {
+        let (root, contexts) = self.into_parts();
+        contexts
+            .into_iter()
+            .rev()
+            .fold(anyhow::Error::new(root), anyhow::Error::context)
+    }
+
+    /// Converts an [anyhow::Error] built on top of this error or its
+    /// root error, turning anyhow's contexts into context frames.
+    ///
+    /// Gives the error back if it was built on top of something else.
+    #[track_caller]
+    pub fn try_from_anyhow(error: anyhow::Error) -> Result<Self, anyhow::Error> {
+        try_from_anyhow(error)
+    }
+}
+
+/// Turns the context layers of `error` back into frames, on top of either a
+/// `W` wrapper or its root error.
+#[doc(hidden)]
+#[track_caller]
+pub fn try_from_anyhow<W, E>(error: anyhow::Error) -> Result<W, anyhow::Error>
+where
+    W: Wrapper<E> + Error + Send + Sync + 'static,
+    E: Error + Send + Sync + 'static,
+{
+    let contexts: Vec<String> = error
+        .chain()
+        .take_while(|e| !e.is::<W>() && !e.is::<E>())
+        .map(ToString::to_string)
+        .collect();
+
+    let mut contextual = match error.downcast::<W>() {
+        Ok(wrapper) => wrapper.into_contextual(),
+        Err(error) => Contextual::new(error.downcast::<E>()?),
+    };
+    for context in contexts.into_iter().rev() {
+        contextual = contextual.add_context(context);
+    }
+    Ok(W::from_contextual(contextual))
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __impl_anyhow {
+    ($out:ident[$($gen:tt)*]($ty:ty)[$($bounds:tt)*]) => {
+        impl<$($gen)*> $out<$($gen)*>
+        where
+            $($bounds)*
+        {
+            /// Converts this error into an [anyhow::Error], re-applying every
+            /// context frame as an anyhow context.
+            pub fn into_anyhow(self) -> $crate::anyhow_compat::__anyhow::Error
+            where
+                for<'__a> $ty: ::std::error::Error + Send + Sync + 'static,
+            {
+                self.0.into_anyhow()
+            }
+
+            /// Converts an [anyhow::Error] built on top of this error or its
+            /// root error, turning anyhow's contexts into context frames.
+            ///
+            /// Gives the error back if it was built on top of something else.
+            #[track_caller]
+            pub fn try_from_anyhow(
+                error: $crate::anyhow_compat::__anyhow::Error,
+            ) -> ::std::result::Result<Self, $crate::anyhow_compat::__anyhow::Error>
+            where
+                for<'__a> $ty: ::std::error::Error + Send + Sync + 'static,
+            {
+                $crate::anyhow_compat::try_from_anyhow(error)
+            }
+        }
+    };
+}
@@ -0,0 +1,73 @@
+//! Typed values attached to context enriched errors.
+//!
+//! Any `Send + Sync + 'static` value can be attached with
+//! [attach](crate::ContextExt::attach), and later retrieved by type through the
+//! wrapper's `request_ref` and `request_all` methods. Attachments are added to
+//! the outermost frame at the time, or to the root if there is no context yet.
+//!
+//! ** Example **
+//! ```
+//! use thiserror::Error;
+//! use thiserror_context::{impl_context, Context, ContextExt};
+//!
+//! #[derive(Debug, Error)]
+//! enum ThisErrorInner {
+//!     #[error("placeholder err")]
+//!     Placeholder,
+//! }
+//! impl_context!(ThisError(ThisErrorInner));
+//!
+//! #[derive(Debug, PartialEq)]
+//! struct HttpStatus(u16);
+//!
+//! fn handler() -> Result<(), ThisError> {
+//!     Err(ThisErrorInner::Placeholder)
+//!         .attach(HttpStatus(404))
+//!         .context("loading user")
+//!         .attach(HttpStatus(500))
+//! }
+//!
+//! let err = handler().unwrap_err();
+//! assert_eq!(err.request_ref::<HttpStatus>(), Some(&HttpStatus(500)));
+//! assert_eq!(
+//!     err.request_all::<HttpStatus>().collect::<Vec<_>>(),
+//!     [&HttpStatus(500), &HttpStatus(404)]
+//! );
+//! assert_eq!(err.request_ref::<String>(), None);
+//! ```
+use std::any::Any;
+use std::fmt::{self, Debug};
+
+/// The values attached to a single frame, or to the root.
+///
+/// Errors rarely get more than a couple of attachments, so they are kept in a
+/// boxed slice, which is cheaper to carry around than a [Vec].
+#[derive(Default)]
+pub struct Attachments(Box<[Box<dyn Any + Send + Sync>]>);
+
+impl Attachments {
+    pub fn push<A: Send + Sync + 'static>(&mut self, attachment: A) {
+        let mut attachments = std::mem::take(&mut self.0).into_vec();
+        attachments.push(Box::new(attachment));
+        self.0 = attachments.into_boxed_slice();
+    }
+
+    /// Every attachment of type `A`, most recently attached first.
+    pub fn iter<A: 'static>(&self) -> impl Iterator<Item = &A> {
+        self.0.iter().rev().filter_map(|a| a.downcast_ref())
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl Debug for Attachments {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Attachments({})", self.len())
+    }
+}
@@ -0,0 +1,21 @@
+//! Backtrace capture for [impl_context](crate::impl_context) wrappers.
+//!
+//! Backtraces are only boxed when they were actually captured, which keeps
+//! the wrappers small enough to be returned by value in a [Result].
+use std::backtrace::{Backtrace, BacktraceStatus};
+
+static DISABLED: Backtrace = Backtrace::disabled();
+
+/// Captures a backtrace, honoring `RUST_LIB_BACKTRACE` and `RUST_BACKTRACE`.
+pub fn capture() -> Option<Box<Backtrace>> {
+    let backtrace = Backtrace::capture();
+    match backtrace.status() {
+        BacktraceStatus::Captured => Some(Box::new(backtrace)),
+        _ => None,
+    }
+}
+
+/// The captured backtrace, or a disabled one if none was captured.
+pub fn or_disabled(backtrace: &Option<Box<Backtrace>>) -> &Backtrace {
+    backtrace.as_deref().unwrap_or(&DISABLED)
+}
@@ -0,0 +1,128 @@
+//! Exposes the context frames of an [impl_context](crate::impl_context) wrapper
+//! through the standard [Error::source] chain.
+//!
+//! Walking the sources of a wrapper yields one [ContextFrame] per context
+//! message, outermost first, followed by the root error and then the root's
+//! own sources. Without context, the chain starts at the root's own sources,
+//! since the wrapper itself displays the root.
+//!
+//! The chain is made of the stored frames themselves: each one points at the
+//! next one towards the root, and the innermost one at the root. Walking it
+//! neither allocates nor copies anything. Since the frames move along with
+//! their wrapper, they are linked again every time the chain is entered.
+use crate::fields::Field;
+use crate::frame::Frame;
+use std::error::Error;
+use std::fmt::{self, Debug, Display};
+use std::marker::PhantomData;
+use std::panic::Location;
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, Ordering};
+
+/// A context frame, as it appears in the [Error::source] chain.
+///
+/// It displays its context message and its source is the next frame, or the
+/// root error once there are no more frames.
+///
+/// Walking the chain reaches the root error, so a frame can only be shared
+/// across threads when the root can:
+///
+/// ```compile_fail
+/// use std::cell::Cell;
+/// use thiserror_context::chain::ContextFrame;
+///
+/// fn assert_sync<T: Sync>() {}
+/// assert_sync::<ContextFrame<Cell<u8>>>();
+/// ```
+#[repr(transparent)]
+pub struct ContextFrame<E> {
+    frame: Frame,
+    root: PhantomData<E>,
+}
+
+impl<E> ContextFrame<E> {
+    fn new(frame: &Frame) -> &Self {
+        // SAFETY: `ContextFrame` is a transparent wrapper around `Frame`.
+        unsafe { &*(frame as *const Frame as *const Self) }
+    }
+
+    /// The context message of this frame.
+    pub fn context(&self) -> &str {
+        self.frame.context()
+    }
+
+    /// Where the context was added.
+    pub fn location(&self) -> &'static Location<'static> {
+        self.frame.location()
+    }
+
+    /// The structured fields added along with the context message.
+    pub fn fields(&self) -> &[Field] {
+        self.frame.fields()
+    }
+}
+
+impl<E> Display for ContextFrame<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.context())
+    }
+}
+
+impl<E> Debug for ContextFrame<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ContextFrame")
+            .field("context", &self.context())
+            .field("location", &self.location())
+            .field("fields", &self.fields())
+            .finish()
+    }
+}
+
+impl<E: Error + 'static> Error for ContextFrame<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        let link = &self.frame.link;
+        let inner = link.inner.load(Ordering::Relaxed);
+        if inner.is_null() {
+            let root = link.root.load(Ordering::Relaxed) as *const E;
+            // SAFETY: this frame was reached through `outermost`, which
+            // pointed the innermost frame at the root of the stack it
+            // borrows from.
+            Some(unsafe { &*root })
+        } else {
+            // SAFETY: `outermost` pointed this frame at the next one, in the
+            // same list, which is borrowed for as long as this frame is.
+            Some(ContextFrame::<E>::new(unsafe { &*inner }))
+        }
+    }
+}
+
+/// Where the next link of the chain is, stored in every [Frame].
+///
+/// Both pointers are refreshed by [outermost] through a shared borrow, so
+/// they are atomics. Concurrent walks of the same frames store the same
+/// values.
+#[derive(Default)]
+pub(crate) struct Link {
+    // The next frame towards the root, or null for the innermost one.
+    inner: AtomicPtr<Frame>,
+    // The root error, only set on the innermost frame.
+    root: AtomicPtr<()>,
+}
+
+/// Links the frames, stored innermost first, and returns the outermost one
+/// as the start of the chain leading to `root`.
+pub(crate) fn outermost<'a, E: Error + 'static>(
+    frames: &'a [Frame],
+    root: &'a E,
+) -> Option<&'a (dyn Error + 'static)> {
+    let innermost = frames.first()?;
+    let link = &innermost.link;
+    link.inner.store(ptr::null_mut(), Ordering::Relaxed);
+    link.root
+        .store(root as *const E as *mut (), Ordering::Relaxed);
+    for pair in frames.windows(2) {
+        let inner = &pair[0] as *const Frame as *mut Frame;
+        pair[1].link.inner.store(inner, Ordering::Relaxed);
+    }
+    Some(ContextFrame::<E>::new(frames.last()?))
+}
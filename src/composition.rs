@@ -46,7 +46,11 @@
 /// let r = outer_call();
 /// assert!(r.is_err());
 /// let e = r.unwrap_err();
-/// assert_eq!(format!("{:?}",e), r#"Inner(Dummy)
+///
+/// // The frames of the inner error move up to the outer one.
+/// let OuterError::Inner(inner) = e.as_ref();
+/// assert_eq!(inner.context_len(), 0);
+/// assert_eq!(format!("{:?}", e.report().backtrace(false).locations(false)), r#"Inner(Dummy)
 ///
 /// Caused by:
 ///     0: context on outer
@@ -55,32 +59,12 @@
 /// ```
 #[macro_export]
 macro_rules! impl_from_carry_context {
-    ($source: ident, $target: ident, $variant: path) => {
+    ($source: ty, $target: ty, $variant: path) => {
         impl From<$source> for $target {
-            fn from(mut value: $source) -> Self {
-                let mut contexts = vec![];
-
-                let inner = loop {
-                    match value {
-                        $source::Base(x) => break x,
-                        $source::Context { context, error } => {
-                            contexts.push(context);
-                            value = *error;
-                        }
-                    }
-                };
-                let inner = $source::Base(inner);
-
-                let mut x = $target::Base($variant(inner));
-
-                for ctx in contexts.into_iter().rev() {
-                    x = $target::Context {
-                        context: ctx,
-                        error: Box::new(x),
-                    };
-                }
-
-                x
+            fn from(value: $source) -> Self {
+                // The frames, backtrace and attachments belong to the root
+                // error as a whole, so they are all moved up to the target.
+                $crate::contextual::carry(value, $variant)
             }
         }
     };
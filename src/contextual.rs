@@ -0,0 +1,338 @@
+//! The generic context enriched error behind every
+//! [impl_context](crate::impl_context) wrapper.
+//!
+//! [Contextual] implements everything once, for any root error type. Each
+//! wrapper is a thin newtype around it, which adds the conversions from its
+//! root error and the [Context](crate::Context) impls, and delegates
+//! everything else.
+//!
+//! Generic code can therefore work with any `Contextual<E>`, and reach it from
+//! a wrapper through [AsRef] or the [Wrapper] trait.
+//!
+//! ** Example **
+//! ```
+//! use std::fmt::Display;
+//! use thiserror::Error;
+//! use thiserror_context::{impl_context, Context, Contextual};
+//!
+//! #[derive(Debug, Error)]
+//! enum ThisErrorInner {
+//!     #[error("placeholder err")]
+//!     Placeholder,
+//! }
+//! impl_context!(ThisError(ThisErrorInner));
+//!
+//! fn describe<E: Display>(err: &Contextual<E>) -> String {
+//!     format!("{err} ({} frames)", err.context_len())
+//! }
+//!
+//! let err = Err::<(), _>(ThisErrorInner::Placeholder)
+//!     .context("loading")
+//!     .unwrap_err();
+//! assert_eq!(describe(err.as_ref()), "placeholder err (1 frames)");
+//!
+//! let err = Contextual::new(std::fmt::Error).add_context("formatting");
+//! assert_eq!(describe(&err), "an error occurred when formatting an argument (1 frames)");
+//! ```
+use crate::display::DisplayChain;
+use crate::fields::{Field, Value};
+use crate::render::{Rendered, ReportRenderer};
+use crate::report::Report;
+use crate::stack::{Frames, Stack};
+use std::backtrace::Backtrace;
+use std::error::Error;
+use std::fmt::{self, Debug, Display};
+use std::panic::Location;
+
+/// A root error of type `E`, along with its context frames.
+pub struct Contextual<E>(Stack<E>);
+
+/// Implemented by every [impl_context](crate::impl_context) wrapper around a
+/// root error of type `E`, to convert it from and into the [Contextual] it
+/// wraps.
+pub trait Wrapper<E>: Sized {
+    fn from_contextual(contextual: Contextual<E>) -> Self;
+
+    fn into_contextual(self) -> Contextual<E>;
+}
+
+impl<E> Wrapper<E> for Contextual<E> {
+    fn from_contextual(contextual: Contextual<E>) -> Self {
+        contextual
+    }
+
+    fn into_contextual(self) -> Contextual<E> {
+        self
+    }
+}
+
+impl<E> Contextual<E> {
+    /// Wraps a root error, capturing a backtrace and the caller's location.
+    #[track_caller]
+    pub fn new(root: E) -> Self {
+        Contextual(Stack::new(root))
+    }
+
+    /// Adds a context frame, recorded at the caller's location.
+    #[track_caller]
+    pub fn add_context<C: Display>(mut self, context: C) -> Self {
+        self.0
+            .push(context.to_string(), Location::caller(), Vec::new());
+        self
+    }
+
+    /// A configurable view of this error, formatted through `Debug`.
+    pub fn report(&self) -> Report<'_, E>
+    where
+        E: Error,
+    {
+        Report::new(&self.0)
+    }
+
+    /// Formats the [report](Self::report) with the given renderer through
+    /// `Display`.
+    pub fn render_with<'a, R>(&'a self, renderer: &'a R) -> Rendered<'a, R>
+    where
+        E: Error,
+        R: ReportRenderer + ?Sized,
+    {
+        self.report().render_with(renderer)
+    }
+
+    /// Writes the [report](Self::report) to a [fmt::Write](std::fmt::Write),
+    /// without allocating beyond what the root error's formatting needs.
+    ///
+    /// Resolving a backtrace allocates, so it is left out.
+    pub fn write_report_fmt(&self, w: &mut impl fmt::Write) -> fmt::Result
+    where
+        E: Error,
+    {
+        self.report().backtrace(false).write_to(w)
+    }
+
+    /// Writes the [report](Self::report) to an [io::Write](std::io::Write),
+    /// without allocating beyond what the root error's formatting needs.
+    ///
+    /// Resolving a backtrace allocates, so it is left out.
+    pub fn write_report(&self, w: &mut impl std::io::Write) -> std::io::Result<()>
+    where
+        E: Error,
+    {
+        self.report().backtrace(false).write_to_io(w)
+    }
+
+    /// The context messages followed by the root error on a single line,
+    /// formatted through `Display`.
+    pub fn display_chain(&self) -> DisplayChain<'_, E> {
+        DisplayChain::new(&self.0)
+    }
+
+    /// The backtrace captured when the root error was converted into
+    /// this wrapper.
+    ///
+    /// Whether it was actually captured depends on the
+    /// `RUST_LIB_BACKTRACE` and `RUST_BACKTRACE` environment variables.
+    pub fn backtrace(&self) -> &Backtrace {
+        self.0.backtrace()
+    }
+
+    /// Where the root error was converted into this wrapper.
+    pub fn location(&self) -> &'static Location<'static> {
+        self.0.location()
+    }
+
+    /// The structured fields of every context frame, outermost
+    /// frame first.
+    pub fn fields(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.0.frames().fields()
+    }
+
+    /// Attaches a value to the outermost frame, or to the root if
+    /// no context was added yet.
+    pub fn attach<A: Send + Sync + 'static>(mut self, attachment: A) -> Self {
+        self.0.attach(attachment);
+        self
+    }
+
+    /// The most recently attached value of type `A`, looking from the
+    /// outermost frame to the root.
+    pub fn request_ref<A: 'static>(&self) -> Option<&A> {
+        self.request_all().next()
+    }
+
+    /// Every attached value of type `A`, from the outermost frame to
+    /// the root.
+    pub fn request_all<A: 'static>(&self) -> impl Iterator<Item = &A> {
+        self.0.frames().request_all()
+    }
+
+    /// The root error.
+    pub fn root(&self) -> &E {
+        self.0.root()
+    }
+
+    /// The context messages, outermost first.
+    pub fn contexts(&self) -> impl DoubleEndedIterator<Item = &str> + ExactSizeIterator {
+        self.0.frames().contexts()
+    }
+
+    /// How many context messages were added on top of the root error.
+    pub fn context_len(&self) -> usize {
+        self.0.frames().len()
+    }
+
+    pub fn into_inner(self) -> E {
+        self.0.into_root()
+    }
+
+    /// Splits this error into its root and its context messages,
+    /// outermost first.
+    ///
+    /// Locations, fields, attachments and the backtrace are dropped.
+    pub fn into_parts(self) -> (E, Vec<String>) {
+        self.0.into_parts()
+    }
+
+    /// Builds an error from a root and context messages, outermost
+    /// first, as returned by [into_parts](Self::into_parts).
+    ///
+    /// The root and every frame are recorded at the caller's location.
+    #[track_caller]
+    pub fn from_parts(root: E, contexts: Vec<String>) -> Self {
+        Contextual(Stack::from_parts(root, contexts))
+    }
+
+    /// Moves every frame onto another wrapper, whose root error is built
+    /// from this one.
+    ///
+    /// The backtrace, location and attachments are kept as well.
+    pub fn map_root<W: Wrapper<U>, U>(self, f: impl FnOnce(E) -> U) -> W {
+        W::from_contextual(Contextual(self.0.map_root(|root, _| f(root))))
+    }
+
+    /// Like [map_root](Self::map_root), but `f` may hand the root error back,
+    /// which returns this error unchanged.
+    pub fn try_map_root<W: Wrapper<U>, U>(
+        self,
+        f: impl FnOnce(E) -> Result<U, E>,
+    ) -> Result<W, Self> {
+        match self.0.try_map_root(f) {
+            Ok(stack) => Ok(W::from_contextual(Contextual(stack))),
+            Err(stack) => Err(Contextual(stack)),
+        }
+    }
+
+    /// Formats this error as a struct named `name` with `{:?}`, or as its
+    /// [report](Self::report) with `{:#?}`.
+    #[doc(hidden)]
+    pub fn debug_as(&self, name: &str, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    where
+        E: Error,
+    {
+        if f.alternate() {
+            Debug::fmt(&self.report(), f)
+        } else if self.frames().is_empty() {
+            // Like the report, a nested wrapper without context reads as its
+            // root, e.g. `Inner(Dummy)`.
+            Debug::fmt(self.root(), f)
+        } else {
+            f.debug_struct(name)
+                .field("root", self.root())
+                .field("context", &ContextList(self.frames()))
+                .finish()
+        }
+    }
+
+    // Unlike the methods above, iterators borrowed from the frames don't
+    // capture the lifetimes of `E`, so wrappers return these.
+    #[doc(hidden)]
+    pub fn frames(&self) -> &Frames {
+        self.0.frames()
+    }
+}
+
+/// Adds a context frame to a wrapper, recorded at the caller's location.
+#[doc(hidden)]
+#[track_caller]
+pub fn push<W: Wrapper<E>, E>(wrapper: W, context: String, fields: Vec<Field>) -> W {
+    push_at(wrapper, context, fields, Location::caller())
+}
+
+/// Adds a context frame to a wrapper, recorded at the given location.
+#[doc(hidden)]
+pub fn push_at<W: Wrapper<E>, E>(
+    wrapper: W,
+    context: String,
+    fields: Vec<Field>,
+    location: &'static Location<'static>,
+) -> W {
+    let mut contextual = wrapper.into_contextual();
+    contextual.0.push(context, location, fields);
+    W::from_contextual(contextual)
+}
+
+/// Moves the frames of a wrapper up to another one, whose root error is built
+/// from the first wrapper, now without frames.
+#[doc(hidden)]
+pub fn carry<S, SE, T, TE>(source: S, variant: impl FnOnce(S) -> TE) -> T
+where
+    S: Wrapper<SE>,
+    T: Wrapper<TE>,
+{
+    let stack = source.into_contextual().0.map_root(|root, location| {
+        variant(S::from_contextual(Contextual(Stack::nested(
+            root, location,
+        ))))
+    });
+    T::from_contextual(Contextual(stack))
+}
+
+impl<E> From<E> for Contextual<E> {
+    #[track_caller]
+    fn from(root: E) -> Self {
+        Contextual::new(root)
+    }
+}
+
+impl<E: Error> Debug for Contextual<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.debug_as("Contextual", f)
+    }
+}
+
+/// The context messages of a [Frames], outermost first, as a `Debug` list.
+struct ContextList<'a>(&'a Frames);
+
+impl Debug for ContextList<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.0.contexts()).finish()
+    }
+}
+
+impl<E: Display> Display for Contextual<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            write!(f, "{}", self.display_chain())
+        } else {
+            write!(f, "{}", self.root())
+        }
+    }
+}
+
+impl<E: Error + 'static> Error for Contextual<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.0.source()
+    }
+}
+
+impl<E> AsRef<Contextual<E>> for Contextual<E> {
+    fn as_ref(&self) -> &Contextual<E> {
+        self
+    }
+}
+
+impl<E> AsRef<E> for Contextual<E> {
+    fn as_ref(&self) -> &E {
+        self.root()
+    }
+}
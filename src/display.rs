@@ -0,0 +1,62 @@
+//! Single line views of a context enriched error, formatted through
+//! [Display].
+use crate::stack::Stack;
+use std::fmt::{self, Display};
+
+/// The context messages of an error followed by its root error, on a single
+/// line, outermost first.
+///
+/// This is what the alternate `{:#}` format of every wrapper prints, joined
+/// with `": "`. Use the wrapper's `display_chain` method to change the
+/// separator.
+///
+/// ** Example **
+/// ```
+/// use thiserror::Error;
+/// use thiserror_context::{Context, impl_context};
+///
+/// #[derive(Debug, Error)]
+/// enum ThisErrorInner {
+///     #[error("parse int err")]
+///     ParseInt(#[from] std::num::ParseIntError),
+/// }
+/// impl_context!(ThisError(ThisErrorInner));
+///
+/// let err = "x".parse::<i64>().context("parsing").context("loading").unwrap_err();
+/// assert_eq!(format!("{err}"), "parse int err");
+/// assert_eq!(format!("{err:#}"), "loading: parsing: parse int err");
+/// assert_eq!(
+///     err.display_chain().separator(" <- ").to_string(),
+///     "loading <- parsing <- parse int err"
+/// );
+/// ```
+pub struct DisplayChain<'a, E> {
+    stack: &'a Stack<E>,
+    separator: &'a str,
+}
+
+impl<'a, E> DisplayChain<'a, E> {
+    #[doc(hidden)]
+    pub fn new(stack: &'a Stack<E>) -> Self {
+        DisplayChain {
+            stack,
+            separator: ": ",
+        }
+    }
+
+    /// What to print between two messages. Defaults to `": "`.
+    pub fn separator(mut self, separator: &'a str) -> Self {
+        self.separator = separator;
+        self
+    }
+}
+
+impl<E: Display> Display for DisplayChain<'_, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for context in self.stack.frames().contexts() {
+            write!(f, "{context}{}", self.separator)?;
+        }
+
+        write!(f, "{}", self.stack.root())
+    }
+}
@@ -0,0 +1,135 @@
+//! Structured key/value fields attached to context frames.
+//!
+//! Fields are added with [context_kv](crate::ContextExt::context_kv), usually
+//! built with the [context_fields](crate::context_fields) macro, and can be
+//! read back from the whole chain through the wrapper's `fields` method.
+//!
+//! ** Example **
+//! ```
+//! use thiserror::Error;
+//! use thiserror_context::fields::Value;
+//! use thiserror_context::{context_fields, impl_context, ContextExt};
+//!
+//! #[derive(Debug, Error)]
+//! enum ThisErrorInner {
+//!     #[error("placeholder err")]
+//!     Placeholder,
+//! }
+//! impl_context!(ThisError(ThisErrorInner));
+//!
+//! fn load_user(id: i64) -> Result<(), ThisError> {
+//!     Err(ThisErrorInner::Placeholder)
+//!         .context_kv("loading user", context_fields!(user_id = id, tenant = "acme"))
+//! }
+//!
+//! let err = load_user(1).unwrap_err();
+//! let fields: Vec<_> = err.fields().collect();
+//! assert_eq!(
+//!     fields,
+//!     [("user_id", &Value::I64(1)), ("tenant", &Value::Str("acme".to_string()))]
+//! );
+//!
+//! assert_eq!(format!("{:?}", err.report().backtrace(false).locations(false)), r#"Placeholder
+//!
+//! Caused by:
+//!     0: loading user {user_id=1, tenant="acme"}
+//! "#);
+//! ```
+use std::borrow::Cow;
+use std::fmt::{self, Display};
+
+/// The key and value of a single field.
+pub type Field = (Cow<'static, str>, Value);
+
+/// The value of a field on a context frame.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    I64(i64),
+    U64(u64),
+    F64(f64),
+    Str(String),
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Bool(v) => Display::fmt(v, f),
+            Value::I64(v) => Display::fmt(v, f),
+            Value::U64(v) => Display::fmt(v, f),
+            Value::F64(v) => Display::fmt(v, f),
+            Value::Str(v) => Display::fmt(v, f),
+        }
+    }
+}
+
+macro_rules! impl_from {
+    ($variant:ident: $($ty:ty),*) => {
+        $(
+            impl From<$ty> for Value {
+                fn from(value: $ty) -> Self {
+                    Value::$variant(value.into())
+                }
+            }
+        )*
+    };
+}
+
+impl_from!(Bool: bool);
+impl_from!(I64: i8, i16, i32, i64);
+impl_from!(U64: u8, u16, u32, u64);
+impl_from!(F64: f32, f64);
+impl_from!(Str: &str, String, Cow<'_, str>);
+
+impl From<isize> for Value {
+    fn from(value: isize) -> Self {
+        Value::I64(value as i64)
+    }
+}
+
+impl From<usize> for Value {
+    fn from(value: usize) -> Self {
+        Value::U64(value as u64)
+    }
+}
+
+impl<T: Clone + Into<Value>> From<&T> for Value {
+    fn from(value: &T) -> Self {
+        value.clone().into()
+    }
+}
+
+/// Writes fields as `{key=value, ...}`, quoting string values.
+pub(crate) fn write_fields(f: &mut (impl fmt::Write + ?Sized), fields: &[Field]) -> fmt::Result {
+    f.write_char('{')?;
+    for (i, (key, value)) in fields.iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        match value {
+            Value::Str(v) => write!(f, "{key}={v:?}")?,
+            v => write!(f, "{key}={v}")?,
+        }
+    }
+    f.write_char('}')
+}
+
+/// Builds the fields for a [context_kv](crate::ContextExt::context_kv) call from
+/// `key = value` pairs, where each value can be of a different type.
+///
+/// ** Example **
+/// ```
+/// use thiserror_context::context_fields;
+/// use thiserror_context::fields::Value;
+///
+/// let id = 1;
+/// let fields = context_fields!(user_id = id, tenant = "acme", retry = true);
+/// assert_eq!(fields[0], ("user_id", Value::I64(1)));
+/// assert_eq!(fields[2], ("retry", Value::Bool(true)));
+/// ```
+#[macro_export]
+macro_rules! context_fields {
+    ($($key:ident = $value:expr),* $(,)?) => {
+        [$((stringify!($key), $crate::fields::Value::from($value))),*]
+    };
+}
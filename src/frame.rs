@@ -0,0 +1,71 @@
+//! A single context frame, as stored by [impl_context](crate::impl_context)
+//! wrappers.
+use crate::attachments::Attachments;
+use crate::chain::Link;
+use crate::fields::Field;
+use std::fmt::{self, Debug};
+use std::panic::Location;
+
+/// A context message along with everything recorded when it was added.
+pub struct Frame {
+    context: String,
+    location: &'static Location<'static>,
+    fields: Vec<Field>,
+    attachments: Attachments,
+    pub(crate) link: Link,
+}
+
+impl Frame {
+    pub(crate) fn new(
+        context: String,
+        location: &'static Location<'static>,
+        fields: Vec<Field>,
+    ) -> Self {
+        Frame {
+            context,
+            location,
+            fields,
+            attachments: Attachments::default(),
+            link: Link::default(),
+        }
+    }
+
+    /// The context message.
+    pub fn context(&self) -> &str {
+        &self.context
+    }
+
+    pub(crate) fn into_context(self) -> String {
+        self.context
+    }
+
+    /// Where the context was added.
+    pub fn location(&self) -> &'static Location<'static> {
+        self.location
+    }
+
+    /// The structured fields added along with the context message.
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    /// The values attached while this was the outermost frame.
+    pub fn attachments(&self) -> &Attachments {
+        &self.attachments
+    }
+
+    pub(crate) fn attachments_mut(&mut self) -> &mut Attachments {
+        &mut self.attachments
+    }
+}
+
+impl Debug for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Frame")
+            .field("context", &self.context)
+            .field("location", &self.location)
+            .field("fields", &self.fields)
+            .field("attachments", &self.attachments)
+            .finish()
+    }
+}
@@ -0,0 +1,207 @@
+//! Context for futures, behind the `futures` feature, and for tokio's task and
+//! timeout errors, behind the `tokio` feature.
+//!
+//! [FutureContextExt] adds `context` and `with_context` to any future
+//! resolving to a `Result`, so the context is added once the future
+//! completes. The frame is still recorded where `context` was called, rather
+//! than where the future was polled.
+//!
+//! ** Example **
+//! ```
+//! use thiserror::Error;
+//! use thiserror_context::{impl_context, FutureContextExt};
+//!
+//! #[derive(Debug, Error)]
+//! enum ThisErrorInner {
+//!     #[error("not found")]
+//!     NotFound,
+//! }
+//! impl_context!(ThisError(ThisErrorInner));
+//!
+//! async fn fetch_user(_id: i64) -> Result<String, ThisErrorInner> {
+//!     Err(ThisErrorInner::NotFound)
+//! }
+//!
+//! async fn load_user(id: i64) -> Result<String, ThisError> {
+//!     fetch_user(id).with_context(|| format!("loading user {id}")).await
+//! }
+//!
+//! # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+//! let err = load_user(1).await.unwrap_err();
+//! assert_eq!(err.contexts().collect::<Vec<_>>(), ["loading user 1"]);
+//! # });
+//! ```
+use crate::contextual::{self, Wrapper};
+use std::fmt::Display;
+use std::future::Future;
+use std::panic::Location;
+
+/// Adds context to the error of a future resolving to a `Result`.
+pub trait FutureContextExt<T, E>: Future<Output = Result<T, E>> + Sized {
+    /// Wrap the error value with additional context once the future
+    /// completes.
+    #[track_caller]
+    fn context<W, C, U>(self, context: C) -> impl Future<Output = Result<T, W>>
+    where
+        E: Into<W>,
+        W: Wrapper<U>,
+        C: Display + Send + Sync + 'static;
+
+    /// Wrap the error value with additional context that is evaluated lazily
+    /// only once the future completes with an error.
+    #[track_caller]
+    fn with_context<W, C, F, U>(self, f: F) -> impl Future<Output = Result<T, W>>
+    where
+        E: Into<W>,
+        W: Wrapper<U>,
+        C: Display + Send + Sync + 'static,
+        F: FnOnce() -> C;
+}
+
+impl<Fut, T, E> FutureContextExt<T, E> for Fut
+where
+    Fut: Future<Output = Result<T, E>>,
+{
+    #[track_caller]
+    fn context<W, C, U>(self, context: C) -> impl Future<Output = Result<T, W>>
+    where
+        E: Into<W>,
+        W: Wrapper<U>,
+        C: Display + Send + Sync + 'static,
+    {
+        let location = Location::caller();
+        async move {
+            self.await.map_err(|e| {
+                contextual::push_at(e.into(), context.to_string(), Vec::new(), location)
+            })
+        }
+    }
+
+    #[track_caller]
+    fn with_context<W, C, F, U>(self, f: F) -> impl Future<Output = Result<T, W>>
+    where
+        E: Into<W>,
+        W: Wrapper<U>,
+        C: Display + Send + Sync + 'static,
+        F: FnOnce() -> C,
+    {
+        let location = Location::caller();
+        async move {
+            self.await
+                .map_err(|e| contextual::push_at(e.into(), f().to_string(), Vec::new(), location))
+        }
+    }
+}
+
+/// Turns the [JoinError](tokio::task::JoinError) of a spawned task into a
+/// chosen root error, with the task name as context.
+///
+/// ** Example **
+/// ```
+/// use thiserror::Error;
+/// use thiserror_context::future::JoinErrorContext;
+/// use thiserror_context::impl_context;
+///
+/// #[derive(Debug, Error)]
+/// enum ThisErrorInner {
+///     #[error("task failed")]
+///     Join(#[source] tokio::task::JoinError),
+/// }
+/// impl_context!(ThisError(ThisErrorInner));
+///
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// let handle = tokio::spawn(async { panic!("boom") });
+/// let res: Result<(), ThisError> = handle.await.task_context(ThisErrorInner::Join, "sync-users");
+/// assert_eq!(res.unwrap_err().contexts().next(), Some("joining task sync-users"));
+/// # });
+/// ```
+#[cfg(feature = "tokio")]
+pub trait JoinErrorContext<T> {
+    #[track_caller]
+    fn task_context<W, R, U>(
+        self,
+        variant: impl FnOnce(tokio::task::JoinError) -> R,
+        task: impl Display,
+    ) -> Result<T, W>
+    where
+        R: Into<W>,
+        W: Wrapper<U>;
+}
+
+#[cfg(feature = "tokio")]
+impl<T> JoinErrorContext<T> for Result<T, tokio::task::JoinError> {
+    #[track_caller]
+    fn task_context<W, R, U>(
+        self,
+        variant: impl FnOnce(tokio::task::JoinError) -> R,
+        task: impl Display,
+    ) -> Result<T, W>
+    where
+        R: Into<W>,
+        W: Wrapper<U>,
+    {
+        let location = Location::caller();
+        self.map_err(|e| {
+            let context = format!("joining task {task}");
+            contextual::push_at(variant(e).into(), context, Vec::new(), location)
+        })
+    }
+}
+
+/// Turns the [Elapsed](tokio::time::error::Elapsed) error of a timeout into a
+/// chosen root error, with the timeout duration as context.
+///
+/// ** Example **
+/// ```
+/// use std::time::Duration;
+/// use thiserror::Error;
+/// use thiserror_context::future::ElapsedContext;
+/// use thiserror_context::impl_context;
+///
+/// #[derive(Debug, Error)]
+/// enum ThisErrorInner {
+///     #[error("timed out")]
+///     Timeout(#[source] tokio::time::error::Elapsed),
+/// }
+/// impl_context!(ThisError(ThisErrorInner));
+///
+/// # tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap().block_on(async {
+/// let timeout = Duration::from_millis(10);
+/// let res: Result<(), ThisError> = tokio::time::timeout(timeout, std::future::pending())
+///     .await
+///     .timeout_context(ThisErrorInner::Timeout, timeout);
+/// assert_eq!(res.unwrap_err().contexts().next(), Some("timed out after 10ms"));
+/// # });
+/// ```
+#[cfg(feature = "tokio")]
+pub trait ElapsedContext<T> {
+    #[track_caller]
+    fn timeout_context<W, R, U>(
+        self,
+        variant: impl FnOnce(tokio::time::error::Elapsed) -> R,
+        timeout: std::time::Duration,
+    ) -> Result<T, W>
+    where
+        R: Into<W>,
+        W: Wrapper<U>;
+}
+
+#[cfg(feature = "tokio")]
+impl<T> ElapsedContext<T> for Result<T, tokio::time::error::Elapsed> {
+    #[track_caller]
+    fn timeout_context<W, R, U>(
+        self,
+        variant: impl FnOnce(tokio::time::error::Elapsed) -> R,
+        timeout: std::time::Duration,
+    ) -> Result<T, W>
+    where
+        R: Into<W>,
+        W: Wrapper<U>,
+    {
+        let location = Location::caller();
+        self.map_err(|e| {
+            let context = format!("timed out after {timeout:?}");
+            contextual::push_at(variant(e).into(), context, Vec::new(), location)
+        })
+    }
+}
@@ -0,0 +1,137 @@
+//! A process-wide hook controlling the layout of every
+//! [Report](crate::report::Report), and therefore of the `{:#?}` format of
+//! every [impl_context](crate::impl_context) wrapper.
+//!
+//! A [ReportHandler] chooses the order of the context frames, the indentation
+//! and numbering of each line, and whether the root error is printed through
+//! `Debug` or `Display`. It is installed once, usually at the start of `main`,
+//! with [set_hook]. Reports use [DefaultHandler] until then.
+//!
+//! ** Example **
+//! ```
+//! use std::fmt;
+//! use thiserror::Error;
+//! use thiserror_context::hook::{self, Order, ReportHandler, RootFormat};
+//! use thiserror_context::{Context, impl_context};
+//!
+//! struct Bullets;
+//!
+//! impl ReportHandler for Bullets {
+//!     fn order(&self) -> Order {
+//!         Order::InnermostFirst
+//!     }
+//!
+//!     fn indent(&self) -> &str {
+//!         "  "
+//!     }
+//!
+//!     fn number(&self, f: &mut dyn fmt::Write, _index: usize) -> fmt::Result {
+//!         f.write_str("- ")
+//!     }
+//!
+//!     fn root_format(&self) -> RootFormat {
+//!         RootFormat::Display
+//!     }
+//! }
+//!
+//! #[derive(Debug, Error)]
+//! enum ThisErrorInner {
+//!     #[error("placeholder err")]
+//!     Placeholder,
+//! }
+//! impl_context!(ThisError(ThisErrorInner));
+//!
+//! hook::set_hook(Bullets).unwrap();
+//!
+//! let err = Err::<(), _>(ThisErrorInner::Placeholder)
+//!     .context("inner")
+//!     .context("outer")
+//!     .unwrap_err();
+//! assert_eq!(format!("{:?}", err.report().backtrace(false).locations(false)), r#"placeholder err
+//!
+//! Caused by:
+//!   - inner
+//!   - outer
+//! "#);
+//! ```
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::sync::OnceLock;
+
+static HOOK: OnceLock<Box<dyn ReportHandler>> = OnceLock::new();
+
+/// The order in which context frames are listed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Order {
+    /// The last context added first, down to the one closest to the root.
+    #[default]
+    OutermostFirst,
+    /// The context closest to the root first.
+    InnermostFirst,
+}
+
+/// How the root error is printed on the first line of a report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RootFormat {
+    #[default]
+    Debug,
+    Display,
+}
+
+/// Controls the layout of every report. Each method defaults to the layout
+/// of [DefaultHandler].
+pub trait ReportHandler: Send + Sync + 'static {
+    /// The order of the context frames. Sources of the root error are
+    /// always listed from the root down.
+    fn order(&self) -> Order {
+        Order::OutermostFirst
+    }
+
+    /// What every line of a section starts with.
+    fn indent(&self) -> &str {
+        "    "
+    }
+
+    /// Writes the number of a line, from 0 at the top of its section.
+    fn number(&self, f: &mut dyn fmt::Write, index: usize) -> fmt::Result {
+        write!(f, "{index}: ")
+    }
+
+    /// Whether the root error is printed through `Debug` or `Display`.
+    fn root_format(&self) -> RootFormat {
+        RootFormat::Debug
+    }
+}
+
+/// The handler used until another one is installed with [set_hook].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultHandler;
+
+impl ReportHandler for DefaultHandler {}
+
+/// Installs the handler used by every report in this process.
+///
+/// Fails if a handler was already installed, keeping that one.
+pub fn set_hook(handler: impl ReportHandler) -> Result<(), HookAlreadySet> {
+    HOOK.set(Box::new(handler)).map_err(|_| HookAlreadySet)
+}
+
+/// The installed handler, or [DefaultHandler] if none was.
+pub fn handler() -> &'static dyn ReportHandler {
+    match HOOK.get() {
+        Some(handler) => &**handler,
+        None => &DefaultHandler,
+    }
+}
+
+/// Returned by [set_hook] when a handler was already installed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HookAlreadySet;
+
+impl Display for HookAlreadySet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a report hook is already installed")
+    }
+}
+
+impl Error for HookAlreadySet {}
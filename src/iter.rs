@@ -0,0 +1,265 @@
+//! Context for every failing item of an iterator of `Result`s.
+//!
+//! [IteratorContextExt] adds `context` and `with_context` to any iterator of
+//! `Result`s, turning each error into a wrapper with one more frame. The
+//! closure given to `with_context` receives the position of the failing item,
+//! and `with_index` also records it as an `index` field on the frame.
+//!
+//! Every frame is recorded where the adapter was created.
+//!
+//! ** Example **
+//! ```
+//! use thiserror::Error;
+//! use thiserror_context::fields::Value;
+//! use thiserror_context::{impl_context, IteratorContextExt};
+//!
+//! #[derive(Debug, Error)]
+//! enum ThisErrorInner {
+//!     #[error(transparent)]
+//!     ParseInt(#[from] std::num::ParseIntError),
+//! }
+//! impl_context!(ThisError(ThisErrorInner));
+//!
+//! let lines = ["1", "2", "x"];
+//! let err = lines
+//!     .iter()
+//!     .map(|line| line.parse::<u8>())
+//!     .with_context(|idx| format!("parsing line {}", idx + 1))
+//!     .with_index()
+//!     .collect::<Result<Vec<_>, ThisError>>()
+//!     .unwrap_err();
+//!
+//! assert_eq!(err.contexts().collect::<Vec<_>>(), ["parsing line 3"]);
+//! assert_eq!(err.fields().collect::<Vec<_>>(), [("index", &Value::U64(2))]);
+//! ```
+use crate::contextual::{self, Wrapper};
+use crate::fields::{Field, Value};
+use std::fmt::{self, Display};
+use std::panic::Location;
+
+/// Adds context to every error of an iterator of `Result`s.
+pub trait IteratorContextExt<T, E>: Iterator<Item = Result<T, E>> + Sized {
+    /// Wrap every error with the same additional context.
+    #[track_caller]
+    fn context<W, C, U>(self, context: C) -> ContextIter<Self, C, W>
+    where
+        E: Into<W>,
+        W: Wrapper<U>,
+        C: Display;
+
+    /// Wrap every error with additional context, evaluated lazily from the
+    /// position of the failing item.
+    #[track_caller]
+    fn with_context<W, C, F, U>(self, f: F) -> WithContextIter<Self, F, W>
+    where
+        E: Into<W>,
+        W: Wrapper<U>,
+        C: Display,
+        F: FnMut(usize) -> C;
+}
+
+impl<I, T, E> IteratorContextExt<T, E> for I
+where
+    I: Iterator<Item = Result<T, E>>,
+{
+    #[track_caller]
+    fn context<W, C, U>(self, context: C) -> ContextIter<Self, C, W>
+    where
+        E: Into<W>,
+        W: Wrapper<U>,
+        C: Display,
+    {
+        ContextIter {
+            iter: self,
+            context,
+            position: Position::new(),
+        }
+    }
+
+    #[track_caller]
+    fn with_context<W, C, F, U>(self, f: F) -> WithContextIter<Self, F, W>
+    where
+        E: Into<W>,
+        W: Wrapper<U>,
+        C: Display,
+        F: FnMut(usize) -> C,
+    {
+        WithContextIter {
+            iter: self,
+            f,
+            position: Position::new(),
+        }
+    }
+}
+
+/// The position of the next item of an adapter, and where its frames are
+/// recorded.
+pub(crate) struct Position<W> {
+    next: usize,
+    indexed: bool,
+    location: &'static Location<'static>,
+    // Picked while the root type of the wrapper is known, which the adapters
+    // themselves don't name.
+    push: fn(W, String, Vec<Field>, &'static Location<'static>) -> W,
+}
+
+// Derives would require `W: Clone` and `W: Debug`, and with them the adapters.
+impl<W> Clone for Position<W> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<W> Copy for Position<W> {}
+
+impl<W> fmt::Debug for Position<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Position")
+            .field("next", &self.next)
+            .field("indexed", &self.indexed)
+            .field("location", &self.location)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<W> Position<W> {
+    #[track_caller]
+    pub(crate) fn new<U>() -> Self
+    where
+        W: Wrapper<U>,
+    {
+        Position {
+            next: 0,
+            indexed: false,
+            location: Location::caller(),
+            push: contextual::push_at,
+        }
+    }
+
+    pub(crate) fn with_index(self) -> Self {
+        Position {
+            indexed: true,
+            ..self
+        }
+    }
+
+    /// Adds context to the next item if it failed, and moves past it.
+    pub(crate) fn record<T, E, C>(
+        &mut self,
+        item: Result<T, E>,
+        context: impl FnOnce(usize) -> C,
+    ) -> Result<T, W>
+    where
+        E: Into<W>,
+        C: Display,
+    {
+        let index = self.next;
+        self.next += 1;
+        match item {
+            Ok(t) => Ok(t),
+            Err(e) => {
+                let fields = match self.indexed {
+                    true => vec![("index".into(), Value::U64(index as u64))],
+                    false => Vec::new(),
+                };
+                let context = context(index).to_string();
+                Err((self.push)(e.into(), context, fields, self.location))
+            }
+        }
+    }
+}
+
+/// An iterator adding the same context to every error, returned by
+/// [IteratorContextExt::context].
+#[derive(Debug)]
+pub struct ContextIter<I, C, W> {
+    iter: I,
+    context: C,
+    position: Position<W>,
+}
+
+impl<I: Clone, C: Clone, W> Clone for ContextIter<I, C, W> {
+    fn clone(&self) -> Self {
+        ContextIter {
+            iter: self.iter.clone(),
+            context: self.context.clone(),
+            position: self.position,
+        }
+    }
+}
+
+impl<I, C, W> ContextIter<I, C, W> {
+    /// Also record the position of each failing item as an `index` field.
+    pub fn with_index(self) -> Self {
+        ContextIter {
+            position: self.position.with_index(),
+            ..self
+        }
+    }
+}
+
+impl<I, T, E, C, W> Iterator for ContextIter<I, C, W>
+where
+    I: Iterator<Item = Result<T, E>>,
+    E: Into<W>,
+    C: Display,
+{
+    type Item = Result<T, W>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.iter.next()?;
+        Some(self.position.record(item, |_| &self.context))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+/// An iterator adding lazily evaluated context to every error, returned by
+/// [IteratorContextExt::with_context].
+#[derive(Debug)]
+pub struct WithContextIter<I, F, W> {
+    iter: I,
+    f: F,
+    position: Position<W>,
+}
+
+impl<I: Clone, F: Clone, W> Clone for WithContextIter<I, F, W> {
+    fn clone(&self) -> Self {
+        WithContextIter {
+            iter: self.iter.clone(),
+            f: self.f.clone(),
+            position: self.position,
+        }
+    }
+}
+
+impl<I, F, W> WithContextIter<I, F, W> {
+    /// Also record the position of each failing item as an `index` field.
+    pub fn with_index(self) -> Self {
+        WithContextIter {
+            position: self.position.with_index(),
+            ..self
+        }
+    }
+}
+
+impl<I, T, E, C, F, W> Iterator for WithContextIter<I, F, W>
+where
+    I: Iterator<Item = Result<T, E>>,
+    E: Into<W>,
+    C: Display,
+    F: FnMut(usize) -> C,
+{
+    type Item = Result<T, W>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.iter.next()?;
+        Some(self.position.record(item, &mut self.f))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
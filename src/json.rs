@@ -0,0 +1,118 @@
+//! A machine readable report of [impl_context](crate::impl_context) wrappers,
+//! as a [serde_json::Value], behind the `json` feature.
+//!
+//! Unlike the `serde` feature, this only needs the root error to implement
+//! [Error], so it works for roots wrapping errors such as `sqlx::Error`. It
+//! can't be turned back into an error.
+//!
+//! The report has the following structure, with the context frames outermost
+//! first and the root's sources from the root down:
+//!
+//! ```json
+//! {
+//!   "type": "my_crate::ThisErrorInner",
+//!   "display": "parse int err",
+//!   "debug": "ParseInt(ParseIntError { kind: InvalidDigit })",
+//!   "location": "src/main.rs:10:5",
+//!   "context": [
+//!     { "message": "loading user", "location": "src/main.rs:12:9", "fields": { "user_id": 1 } }
+//!   ],
+//!   "sources": ["invalid digit found in string"]
+//! }
+//! ```
+//!
+//! ** Example **
+//! ```
+//! use thiserror::Error;
+//! use thiserror_context::{context_fields, impl_context, Context, ContextExt};
+//!
+//! #[derive(Debug, Error)]
+//! enum ThisErrorInner {
+//!     #[error("parse int err")]
+//!     ParseInt(#[from] std::num::ParseIntError),
+//! }
+//! impl_context!(ThisError(ThisErrorInner));
+//!
+//! let err: ThisError = "x"
+//!     .parse::<i64>()
+//!     .context_kv("loading user", context_fields!(user_id = 1))
+//!     .unwrap_err();
+//!
+//! let report = err.to_json_report();
+//! assert_eq!(report["display"], "parse int err");
+//! assert_eq!(report["context"][0]["message"], "loading user");
+//! assert_eq!(report["context"][0]["fields"]["user_id"], 1);
+//! assert_eq!(report["sources"][0], "invalid digit found in string");
+//! ```
+use crate::contextual::Contextual;
+use crate::fields::{Field, Value};
+use serde_json::{json, Map};
+use std::error::Error;
+
+#[doc(hidden)]
+pub use serde_json as __serde_json;
+
+impl<E: Error> Contextual<E> {
+    /// A machine readable report of this error. See the [json](crate::json)
+    /// module for its structure.
+    pub fn to_json_report(&self) -> serde_json::Value {
+        let root = self.root();
+        let context: Vec<_> = self
+            .frames()
+            .iter()
+            .map(|frame| {
+                json!({
+                    "message": frame.context(),
+                    "location": frame.location().to_string(),
+                    "fields": fields_to_json(frame.fields()),
+                })
+            })
+            .collect();
+        let sources: Vec<_> = std::iter::successors(root.source(), |&e| e.source())
+            .map(|e| e.to_string())
+            .collect();
+
+        json!({
+            "type": std::any::type_name::<E>(),
+            "display": root.to_string(),
+            "debug": format!("{root:?}"),
+            "location": self.location().to_string(),
+            "context": context,
+            "sources": sources,
+        })
+    }
+}
+
+fn fields_to_json(fields: &[Field]) -> serde_json::Value {
+    let fields = fields.iter().map(|(key, value)| {
+        let value = match value {
+            Value::Bool(v) => json!(v),
+            Value::I64(v) => json!(v),
+            Value::U64(v) => json!(v),
+            Value::F64(v) => json!(v),
+            Value::Str(v) => json!(v),
+        };
+        (key.to_string(), value)
+    });
+    serde_json::Value::Object(fields.collect::<Map<_, _>>())
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __impl_json {
+    ($out:ident[$($gen:tt)*]($ty:ty)[$($bounds:tt)*]) => {
+        impl<$($gen)*> $out<$($gen)*>
+        where
+            $($bounds)*
+        {
+            /// A machine readable report of this error. See the `json`
+            /// module for its structure.
+            pub fn to_json_report(&self) -> $crate::json::__serde_json::Value
+            where
+                for<'__a> $ty: ::std::error::Error,
+            {
+                self.0.to_json_report()
+            }
+        }
+    };
+}
@@ -205,6 +205,8 @@
 //!
 //! [context](Context::context) adds static context to the error, while
 //! [with_context](Context::with_context) adds dynamic context to the error.
+//! [ContextExt] adds `context_kv` and `attach`, see
+//! [Structured fields](#structured-fields) and [Attachments](#attachments).
 //!
 //! ```
 //! use thiserror::Error;
@@ -230,7 +232,7 @@
 //! let res = t(1);
 //! assert!(res.is_err());
 //! let err = res.unwrap_err();
-//! let debug_repr = format!("{:#?}", err);
+//! let debug_repr = format!("{:#?}", err.report().backtrace(false).locations(false));
 //! assert_eq!(r#"Placeholder
 //!
 //! Caused by:
@@ -239,209 +241,1730 @@
 //! "#, debug_repr);
 //! ```
 //!
+//! # Contextual
+//!
+//! Every wrapper is a thin newtype around the generic [Contextual], which
+//! implements the behavior once for any root error type. Generic code can accept
+//! a `&Contextual<E>`, which wrappers provide through `AsRef`, and
+//! `Contextual<E>` can also be used directly when a named wrapper isn't needed.
+//!
 //! # Nesting
 //!
 //! Context enriched errors can be nested and they will preserve their
 //! context messages when converting from a child error type to a parent.
 //!
 //! See [impl_from_carry_context] for more information.
+//!
+//! # Inspecting context
+//!
+//! The context messages can be read without going through the `Debug` output:
+//! `contexts` iterates over them outermost first, `context_len` counts them and
+//! `root` borrows the root error. `into_parts` and `from_parts` split an error
+//! into its root and messages and put it back together.
+//!
+//! ```
+//! use thiserror::Error;
+//! use thiserror_context::{Context, impl_context};
+//!
+//! #[derive(Debug, Error)]
+//! enum ThisErrorInner {
+//!     #[error("placeholder err")]
+//!     Placeholder,
+//! }
+//! impl_context!(ThisError(ThisErrorInner));
+//!
+//! let err = Err::<(), _>(ThisErrorInner::Placeholder)
+//!     .context("inner")
+//!     .context("outer")
+//!     .unwrap_err();
+//!
+//! assert_eq!(err.context_len(), 2);
+//! assert_eq!(err.contexts().collect::<Vec<_>>(), ["outer", "inner"]);
+//! assert_eq!(err.contexts().rev().collect::<Vec<_>>(), ["inner", "outer"]);
+//!
+//! let (root, contexts) = err.into_parts();
+//! let err = ThisError::from_parts(root, contexts);
+//! assert!(matches!(err.root(), ThisErrorInner::Placeholder));
+//! assert_eq!(err.contexts().next(), Some("outer"));
+//! ```
+//!
+//! # Debug
+//!
+//! `{:?}` prints a compact single line, such as
+//! `ThisError { root: Placeholder, context: ["for id 1", "some static context"] }`,
+//! so errors stay readable in `assert_eq!`, `unwrap` and log lines. An error
+//! without context prints as its root, e.g. `Placeholder`. `{:#?}`, which
+//! `dbg!` uses, prints the multi-line report shown above, which `report` can
+//! configure. See [report::Report].
+//!
+//! # Report hook
+//!
+//! The layout of the `{:#?}` report can be changed for the whole process by
+//! installing a [hook::ReportHandler] with [hook::set_hook], usually at the
+//! start of `main`. A handler chooses the order of the context frames, the
+//! indentation and numbering of each line, and whether the root error is
+//! printed through `Debug` or `Display`, without touching any error type.
+//!
+//! # Renderers
+//!
+//! A [render::ReportRenderer] writes the report to any `fmt::Write`, given the
+//! root error, the context frames and the root's source chain. Besides the
+//! plain text of `{:#?}`, the crate ships Markdown and HTML renderers, applied
+//! with `err.render_with(&MarkdownRenderer)`. See the [render] module.
+//!
+//! # Colors
+//!
+//! With the `color` feature, `render::ColorRenderer` prints the `{:#?}` report
+//! with ANSI colors, for the error printed at the end of `main`. Its `auto`
+//! constructor respects `NO_COLOR` and only colors terminals.
+//!
+//! # Writing reports
+//!
+//! `write_report` writes the `{:#?}` report to an `io::Write`, and
+//! `write_report_fmt` to a `fmt::Write`. The frames are walked in place and
+//! written directly, so nothing is allocated beyond what the root error's own
+//! formatting needs, which suits crash reporters writing into fixed buffers.
+//! Resolving a backtrace allocates, so both leave it out. `report()` offers
+//! `write_to_io` and `write_to` to write a report that includes it.
+//!
+//! # Display
+//!
+//! `{}` prints the root error alone, while `{:#}` follows anyhow and prints
+//! every context message before it on a single line, as in
+//! `loading user 1: parsing id: invalid digit`. `display_chain` returns the
+//! same view with a configurable separator. See [display::DisplayChain].
+//!
+//! # Re-classifying errors
+//!
+//! `map_root` moves every context frame onto another wrapper, with a root error
+//! built from the current one, while `try_map_root` only does so when the
+//! closure accepts the root. This keeps the trail when re-classifying errors at
+//! a module boundary.
+//!
+//! ```
+//! use thiserror::Error;
+//! use thiserror_context::{Context, impl_context};
+//!
+//! #[derive(Debug, Error)]
+//! enum DbErrorInner {
+//!     #[error("row not found")]
+//!     RowNotFound,
+//! }
+//! impl_context!(DbError(DbErrorInner));
+//!
+//! #[derive(Debug, Error)]
+//! enum UserErrorInner {
+//!     #[error("user not found")]
+//!     NotFound,
+//! }
+//! impl_context!(UserError(UserErrorInner));
+//!
+//! let err: DbError = Err::<(), _>(DbErrorInner::RowNotFound)
+//!     .context("loading user 1")
+//!     .unwrap_err();
+//!
+//! let err: UserError = err
+//!     .try_map_root(|root| match root {
+//!         DbErrorInner::RowNotFound => Ok(UserErrorInner::NotFound),
+//!     })
+//!     .unwrap();
+//! assert!(matches!(err.root(), UserErrorInner::NotFound));
+//! assert_eq!(err.contexts().collect::<Vec<_>>(), ["loading user 1"]);
+//! ```
+//!
+//! # Choosing the wrapper
+//!
+//! When several wrappers accept the same error, `context` relies on the return
+//! type to know which one to build. Where that isn't enough, such as in closures
+//! or `let` bindings, [ContextAs] names it explicitly:
+//! `"1".parse::<i64>().context_as::<ThisError>("parsing id")`.
+//!
+//! # Options
+//!
+//! [OptionContext] turns a `None` into a wrapper, from a chosen root error and
+//! with the context frame already added, like
+//! `user.context_or(ThisErrorInner::NotFound, "loading user 1")`.
+//!
+//! # Locations
+//!
+//! Every `context` and `with_context` call records its call site, and so does
+//! the conversion of the root error into the wrapper. They are printed next to
+//! each frame of the `Debug` report, like `0: for id 1 (src/users.rs:42:10)`,
+//! which is much cheaper than capturing a backtrace on hot paths.
+//!
+//! # Structured fields
+//!
+//! Besides a message, a context frame can carry typed key/value fields, which
+//! log pipelines can index instead of parsing them out of sentences. They are
+//! added with `context_kv`, from the [ContextExt] trait.
+//!
+//! ```
+//! use thiserror::Error;
+//! use thiserror_context::{context_fields, impl_context, ContextExt};
+//!
+//! #[derive(Debug, Error)]
+//! enum ThisErrorInner {
+//!     #[error("placeholder err")]
+//!     Placeholder,
+//! }
+//! impl_context!(ThisError(ThisErrorInner));
+//!
+//! fn load_user(id: i64) -> Result<(), ThisError> {
+//!     Err(ThisErrorInner::Placeholder)
+//!         .context_kv("loading user", context_fields!(user_id = id, tenant = "acme"))
+//! }
+//!
+//! let err = load_user(1).unwrap_err();
+//! for (key, value) in err.fields() {
+//!     println!("{key}={value}");
+//! }
+//! ```
+//!
+//! # Attachments
+//!
+//! Arbitrary `Send + Sync + 'static` values can be attached to an error with
+//! `attach`, from the [ContextExt] trait, and retrieved later by their type
+//! with `request_ref` and `request_all`. This lets middleware pull metadata
+//! such as an HTTP status or a request id out of an error without parsing
+//! strings. See [attachments].
+//!
+//! # Backtraces
+//!
+//! A `std::backtrace::Backtrace` is captured whenever a root error is converted
+//! into a wrapper, honoring `RUST_LIB_BACKTRACE` and `RUST_BACKTRACE` the same
+//! way [anyhow] does. It is available through the wrapper's `backtrace` method
+//! and printed at the end of the `Debug` report.
+//!
+//! # Derive
+//!
+//! With the `derive` feature, `#[derive(ErrorContext)]` is an alternative to
+//! [impl_context] that defines the wrapper next to the error type, with its own
+//! visibility, docs and attributes:
+//!
+//! ```ignore
+//! #[derive(Debug, Error, ErrorContext)]
+//! #[context(wrapper = "UserError", vis = "pub(crate)")]
+//! pub enum UserErrorInner {
+//!     #[error("user not found")]
+//!     NotFound,
+//! }
+//! ```
+//!
+//! Generic error types are supported as well.
+//!
+//! # anyhow
+//!
+//! With the `anyhow` feature, wrappers get `into_anyhow`, which re-applies every
+//! context frame as an anyhow context, and `try_from_anyhow`, which turns
+//! anyhow's contexts back into frames. This allows migrating from anyhow one
+//! module at a time. See the `anyhow_compat` module for the details.
+//!
+//! # Futures
+//!
+//! With the `futures` feature, `FutureContextExt` adds `context` and
+//! `with_context` to futures resolving to a `Result`, recording the frame where
+//! the adapter was created. The `tokio` feature also adds helpers turning a
+//! task's `JoinError` or a timeout's `Elapsed` into a root error, with the task
+//! name or the duration as context. See the `future` module for the details.
+//!
+//! # Iterators and streams
+//!
+//! [IteratorContextExt] adds `context` and `with_context` to iterators of
+//! `Result`s, adding a frame to every error. The `with_context` closure
+//! receives the position of the failing item, and `with_index` also records it
+//! as an `index` field. With the `futures` feature, `TryStreamContextExt` does
+//! the same for streams. See the [iter] module for the details.
+//!
+//! # Serialization
+//!
+//! With the `serde` feature, wrappers implement `Serialize` and `Deserialize`
+//! whenever their root error does, carrying the context messages along with a
+//! format version. See the `serialization` module for the exact structure.
+//!
+//! # JSON reports
+//!
+//! With the `json` feature, `to_json_report` returns a `serde_json::Value`
+//! with the root's `Display`, `Debug` and type name, the context frames and
+//! the root's source chain, for log shippers ingesting JSON lines. Unlike the
+//! `serde` feature, the root error doesn't need to implement `Serialize`. See
+//! the `json` module for the exact structure.
+//!
+//! # Source chain
+//!
+//! Context frames are also exposed through [std::error::Error::source], so
+//! anything that walks the standard chain (such as [anyhow]'s `{:#}`) sees
+//! every context message, followed by the root error and its own sources.
+//! Since the wrapper displays its root error, anyhow's `{:#}` shows the root
+//! both first and after the context messages. Without context, the chain
+//! starts at the root's own sources instead.
+//!
+//! The [Debug] report lists those root sources as well, in a separate section
+//! after the context frames. See [report::Report] to turn it off.
+//!
+//! The frames are stored in a flat list next to the root error, so adding
+//! context is a push and arbitrarily deep chains are dropped without
+//! recursion. The source chain is made of those same frames, so walking it
+//! doesn't allocate.
+//!
+//! ```
+//! use std::error::Error as _;
+//! use thiserror::Error;
+//! use thiserror_context::{Context, impl_context};
+//!
+//! #[derive(Debug, Error)]
+//! enum ThisErrorInner {
+//!     #[error("placeholder err")]
+//!     Placeholder,
+//! }
+//! impl_context!(ThisError(ThisErrorInner));
+//!
+//! let err = Err::<(), _>(ThisErrorInner::Placeholder)
+//!     .context("inner")
+//!     .context("outer")
+//!     .unwrap_err();
+//!
+//! let chain: Vec<String> = std::iter::successors(err.source(), |&e| e.source())
+//!     .map(|e| e.to_string())
+//!     .collect();
+//! assert_eq!(chain, ["outer", "inner", "placeholder err"]);
+//! ```
+#[cfg(feature = "anyhow")]
+pub mod anyhow_compat;
+pub mod attachments;
+#[doc(hidden)]
+pub mod backtrace;
+pub mod chain;
 pub mod composition;
+pub mod contextual;
+pub mod display;
+pub mod fields;
+pub mod frame;
+#[cfg(feature = "futures")]
+pub mod future;
+pub mod hook;
+pub mod iter;
+#[cfg(feature = "json")]
+pub mod json;
+pub mod render;
+pub mod report;
+#[cfg(feature = "serde")]
+pub mod serialization;
+#[doc(hidden)]
+pub mod stack;
+#[cfg(feature = "futures")]
+pub mod stream;
+
+pub use contextual::Contextual;
+#[cfg(feature = "futures")]
+pub use future::FutureContextExt;
+pub use iter::IteratorContextExt;
+#[cfg(feature = "futures")]
+pub use stream::TryStreamContextExt;
+#[cfg(feature = "derive")]
+pub use thiserror_context_derive::ErrorContext;
+
+use std::borrow::Cow;
+use std::fmt::Display;
+
+/// Defines a new struct that wraps the error type, allowing additional
+/// context to be added.
+///
+/// The struct is a thin newtype around [Contextual], which implements the
+/// behavior once for every wrapper.
+///
+/// The wrapped error type is intended to be a [thiserror](https://docs.rs/thiserror) enum, but
+/// should work with any error type.
+///
+/// The wrapper will implement all the [From] methods that the wrapped
+/// error type implements.
+///
+/// Ultimately, this allows [anyhow](https://docs.rs/anyhow)-like `context` and `with_context`
+/// calls on a strongly typed error.
+///
+/// ** Example **
+/// ```ignore
+/// impl_context!(DummyError(DummyErrorInner));
+/// ```
+///
+/// Generic error types are supported too, with their lifetimes first and an
+/// optional where-clause. Everything works with borrowed error types, except
+/// [std::error::Error], which is only implemented when the wrapped error is
+/// `'static`: its source chain leads to the root error.
+///
+/// ** Example **
+/// ```
+/// use std::fmt::Debug;
+/// use thiserror::Error;
+/// use thiserror_context::{Context, impl_context};
+///
+/// #[derive(Debug, Error)]
+/// pub enum StoreErrorInner<K: Debug> {
+///     #[error("key not found: {0:?}")]
+///     NotFound(K),
+/// }
+/// impl_context!(StoreError<K>(StoreErrorInner<K>) where K: Debug);
+///
+/// #[derive(Debug, Error)]
+/// #[error("invalid token {0:?}")]
+/// pub struct ParseErrorInner<'a>(&'a str);
+/// impl_context!(ParseError<'a>(ParseErrorInner<'a>));
+///
+/// fn get(key: u32) -> Result<(), StoreError<u32>> {
+///     Err(StoreErrorInner::NotFound(key)).context("getting")
+/// }
+///
+/// let err = get(1).unwrap_err();
+/// assert_eq!(err.contexts().collect::<Vec<_>>(), ["getting"]);
+/// ```
+#[macro_export]
+macro_rules! impl_context {
+    ($out:ident($ty:ty)) => {
+        pub struct $out($crate::Contextual<$ty>);
+
+        $crate::__impl_context!($out[]($ty)[]);
+    };
+    (
+        $out:ident<$($lt:lifetime),* $(,)? $($param:ident),*>($ty:ty)
+        $(where $($bounds:tt)+)?
+    ) => {
+        pub struct $out<$($lt,)* $($param),*>($crate::Contextual<$ty>)
+        $(where $($bounds)+)?;
+
+        $crate::__impl_context!($out[$($lt,)* $($param,)*]($ty)[$($($bounds)+)?]);
+    };
+}
+
+/// The impls behind [impl_context], for a `$out` newtype around a
+/// `Contextual<$ty>` that was already defined.
+///
+/// The generic parameters of `$out` are given with a trailing comma, and its
+/// where-clause without one. Generic parameters of the impls themselves are
+/// prefixed with `__`, so they can't clash with those of `$out`.
+///
+/// The behavior itself is implemented once on [Contextual], and `$out` only
+/// delegates to it.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __impl_context {
+    ($out:ident[$($gen:tt)*]($ty:ty)[$($bounds:tt)*]) => {
+        impl<$($gen)* __T: ::std::convert::Into<$ty>> ::std::convert::From<__T> for $out<$($gen)*>
+        where
+            $($bounds)*
+        {
+            #[track_caller]
+            fn from(value: __T) -> Self {
+                $out($crate::Contextual::new(value.into()))
+            }
+        }
+
+        impl<$($gen)*> $crate::contextual::Wrapper<$ty> for $out<$($gen)*>
+        where
+            $($bounds)*
+        {
+            fn from_contextual(contextual: $crate::Contextual<$ty>) -> Self {
+                $out(contextual)
+            }
+
+            fn into_contextual(self) -> $crate::Contextual<$ty> {
+                self.0
+            }
+        }
+
+        impl<$($gen)*> $out<$($gen)*>
+        where
+            $($bounds)*
+        {
+            /// A configurable view of this error, formatted through `Debug`.
+            pub fn report(&self) -> $crate::report::Report<'_, $ty>
+            where
+                $ty: ::std::error::Error,
+            {
+                self.0.report()
+            }
+
+            /// Formats the [report](Self::report) with the given renderer
+            /// through `Display`.
+            pub fn render_with<'__a, __R>(
+                &'__a self,
+                renderer: &'__a __R,
+            ) -> $crate::render::Rendered<'__a, __R>
+            where
+                $ty: ::std::error::Error,
+                __R: $crate::render::ReportRenderer + ?Sized,
+            {
+                self.0.render_with(renderer)
+            }
+
+            /// Writes the [report](Self::report) to a
+            /// [fmt::Write](::std::fmt::Write), without allocating beyond what
+            /// the root error's formatting needs.
+            ///
+            /// Resolving a backtrace allocates, so it is left out.
+            pub fn write_report_fmt(
+                &self,
+                w: &mut impl ::std::fmt::Write,
+            ) -> ::std::fmt::Result
+            where
+                $ty: ::std::error::Error,
+            {
+                self.0.write_report_fmt(w)
+            }
+
+            /// Writes the [report](Self::report) to an
+            /// [io::Write](::std::io::Write), without allocating beyond what
+            /// the root error's formatting needs.
+            ///
+            /// Resolving a backtrace allocates, so it is left out.
+            pub fn write_report(
+                &self,
+                w: &mut impl ::std::io::Write,
+            ) -> ::std::io::Result<()>
+            where
+                $ty: ::std::error::Error,
+            {
+                self.0.write_report(w)
+            }
+
+            /// The context messages followed by the root error on a single
+            /// line, formatted through `Display`.
+            pub fn display_chain(&self) -> $crate::display::DisplayChain<'_, $ty> {
+                self.0.display_chain()
+            }
+
+            /// The backtrace captured when the root error was converted into
+            /// this wrapper.
+            ///
+            /// Whether it was actually captured depends on the
+            /// `RUST_LIB_BACKTRACE` and `RUST_BACKTRACE` environment variables.
+            pub fn backtrace(&self) -> &::std::backtrace::Backtrace {
+                self.0.backtrace()
+            }
+
+            /// Where the root error was converted into this wrapper.
+            pub fn location(&self) -> &'static ::std::panic::Location<'static> {
+                self.0.location()
+            }
+
+            /// The structured fields of every context frame, outermost
+            /// frame first.
+            pub fn fields(&self) -> impl Iterator<Item = (&str, &$crate::fields::Value)> {
+                self.0.frames().fields()
+            }
+
+            /// Attaches a value to the outermost frame, or to the root if
+            /// no context was added yet.
+            pub fn attach<__A: Send + Sync + 'static>(self, attachment: __A) -> Self {
+                $out(self.0.attach(attachment))
+            }
+
+            /// The most recently attached value of type `A`, looking from the
+            /// outermost frame to the root.
+            pub fn request_ref<__A: 'static>(&self) -> ::std::option::Option<&__A> {
+                self.0.request_ref()
+            }
+
+            /// Every attached value of type `A`, from the outermost frame to
+            /// the root.
+            pub fn request_all<__A: 'static>(&self) -> impl Iterator<Item = &__A> {
+                self.0.frames().request_all()
+            }
+
+            /// The root error.
+            pub fn root(&self) -> &$ty {
+                self.0.root()
+            }
+
+            /// The context messages, outermost first.
+            pub fn contexts(&self) -> impl DoubleEndedIterator<Item = &str> + ExactSizeIterator {
+                self.0.frames().contexts()
+            }
+
+            /// How many context messages were added on top of the root error.
+            pub fn context_len(&self) -> usize {
+                self.0.context_len()
+            }
+
+            pub fn into_inner(self) -> $ty {
+                self.0.into_inner()
+            }
+
+            /// Splits this error into its root and its context messages,
+            /// outermost first.
+            ///
+            /// Locations, fields, attachments and the backtrace are dropped.
+            pub fn into_parts(self) -> ($ty, ::std::vec::Vec<String>) {
+                self.0.into_parts()
+            }
+
+            /// Moves every frame onto another wrapper, whose root error is
+            /// built from this one.
+            ///
+            /// The backtrace, location and attachments are kept as well.
+            pub fn map_root<__W, __U>(self, f: impl FnOnce($ty) -> __U) -> __W
+            where
+                __W: $crate::contextual::Wrapper<__U>,
+            {
+                self.0.map_root(f)
+            }
+
+            /// Like [map_root](Self::map_root), but `f` may hand the root
+            /// error back, which returns this error unchanged.
+            pub fn try_map_root<__W, __U>(
+                self,
+                f: impl FnOnce($ty) -> ::std::result::Result<__U, $ty>,
+            ) -> ::std::result::Result<__W, Self>
+            where
+                __W: $crate::contextual::Wrapper<__U>,
+            {
+                self.0.try_map_root(f).map_err($out)
+            }
+
+            /// Builds an error from a root and context messages, outermost
+            /// first, as returned by [into_parts](Self::into_parts).
+            ///
+            /// The root and every frame are recorded at the caller's location.
+            #[track_caller]
+            pub fn from_parts(root: $ty, contexts: ::std::vec::Vec<String>) -> Self {
+                $out($crate::Contextual::from_parts(root, contexts))
+            }
+        }
+
+        impl<$($gen)*> ::std::fmt::Debug for $out<$($gen)*>
+        where
+            $ty: ::std::error::Error,
+            $($bounds)*
+        {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                self.0.debug_as(stringify!($out), f)
+            }
+        }
+
+        impl<$($gen)*> ::std::fmt::Display for $out<$($gen)*>
+        where
+            $ty: ::std::fmt::Display,
+            $($bounds)*
+        {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                ::std::fmt::Display::fmt(&self.0, f)
+            }
+        }
+
+        impl<$($gen)*> ::std::error::Error for $out<$($gen)*>
+        where
+            $ty: ::std::error::Error + 'static,
+            $($bounds)*
+        {
+            fn source(&self) -> ::std::option::Option<&(dyn ::std::error::Error + 'static)> {
+                ::std::error::Error::source(&self.0)
+            }
+        }
+
+        impl<$($gen)*> ::std::convert::AsRef<$ty> for $out<$($gen)*>
+        where
+            $($bounds)*
+        {
+            fn as_ref(&self) -> &$ty {
+                self.0.root()
+            }
+        }
+
+        impl<$($gen)*> ::std::convert::AsRef<$crate::Contextual<$ty>> for $out<$($gen)*>
+        where
+            $($bounds)*
+        {
+            fn as_ref(&self) -> &$crate::Contextual<$ty> {
+                &self.0
+            }
+        }
+
+        // Implemented for each wrapper rather than for every `Wrapper`, so the
+        // wrapper can be inferred when only one of them accepts the error. The
+        // same goes for `ContextExt`.
+        impl<$($gen)* __Z, __E> $crate::Context<$out<$($gen)*>, __Z, __E>
+            for ::std::result::Result<__Z, __E>
+        where
+            __E: ::std::convert::Into<$out<$($gen)*>>,
+            $($bounds)*
+        {
+            #[track_caller]
+            fn context<__C>(self, context: __C) -> ::std::result::Result<__Z, $out<$($gen)*>>
+            where
+                __C: ::std::fmt::Display + Send + Sync + 'static,
+            {
+                match self {
+                    Ok(t) => Ok(t),
+                    Err(e) => Err($crate::contextual::push(
+                        e.into(),
+                        context.to_string(),
+                        ::std::vec::Vec::new(),
+                    )),
+                }
+            }
+
+            #[track_caller]
+            fn with_context<__C, __F>(self, f: __F) -> ::std::result::Result<__Z, $out<$($gen)*>>
+            where
+                __C: ::std::fmt::Display + Send + Sync + 'static,
+                __F: FnOnce() -> __C,
+            {
+                match self {
+                    Ok(t) => Ok(t),
+                    Err(e) => Err($crate::contextual::push(
+                        e.into(),
+                        f().to_string(),
+                        ::std::vec::Vec::new(),
+                    )),
+                }
+            }
+
+        }
+
+        impl<$($gen)* __Z, __E> $crate::ContextExt<$out<$($gen)*>, __Z, __E>
+            for ::std::result::Result<__Z, __E>
+        where
+            __E: ::std::convert::Into<$out<$($gen)*>>,
+            $($bounds)*
+        {
+            #[track_caller]
+            fn context_kv<__C, __I, __K, __V>(
+                self,
+                context: __C,
+                fields: __I,
+            ) -> ::std::result::Result<__Z, $out<$($gen)*>>
+            where
+                __C: ::std::fmt::Display + Send + Sync + 'static,
+                __I: IntoIterator<Item = (__K, __V)>,
+                __K: ::std::convert::Into<::std::borrow::Cow<'static, str>>,
+                __V: ::std::convert::Into<$crate::fields::Value>,
+            {
+                match self {
+                    Ok(t) => Ok(t),
+                    Err(e) => Err($crate::contextual::push(
+                        e.into(),
+                        context.to_string(),
+                        fields
+                            .into_iter()
+                            .map(|(k, v)| (k.into(), v.into()))
+                            .collect(),
+                    )),
+                }
+            }
+
+            #[track_caller]
+            fn attach<__A>(self, attachment: __A) -> ::std::result::Result<__Z, $out<$($gen)*>>
+            where
+                __A: Send + Sync + 'static,
+            {
+                match self {
+                    Ok(t) => Ok(t),
+                    Err(e) => {
+                        let out: $out<$($gen)*> = e.into();
+                        Err(out.attach(attachment))
+                    }
+                }
+            }
+        }
+
+        $crate::__impl_serde!($out[$($gen)*]($ty)[$($bounds)*]);
+        $crate::__impl_anyhow!($out[$($gen)*]($ty)[$($bounds)*]);
+        $crate::__impl_json!($out[$($gen)*]($ty)[$($bounds)*]);
+    };
+}
+
+#[cfg(not(feature = "anyhow"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __impl_anyhow {
+    ($out:ident[$($gen:tt)*]($ty:ty)[$($bounds:tt)*]) => {};
+}
+
+#[cfg(not(feature = "json"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __impl_json {
+    ($out:ident[$($gen:tt)*]($ty:ty)[$($bounds:tt)*]) => {};
+}
+
+#[cfg(not(feature = "serde"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __impl_serde {
+    ($out:ident[$($gen:tt)*]($ty:ty)[$($bounds:tt)*]) => {};
+}
+
+pub trait Context<W, T, E>
+where
+    E: Into<W>,
+{
+    /// Wrap the error value with additional context.
+    #[track_caller]
+    fn context<C>(self, context: C) -> Result<T, W>
+    where
+        C: Display + Send + Sync + 'static;
+
+    /// Wrap the error value with additional context that is evaluated lazily
+    /// only once an error does occur.
+    #[track_caller]
+    fn with_context<C, F>(self, f: F) -> Result<T, W>
+    where
+        C: Display + Send + Sync + 'static,
+        F: FnOnce() -> C;
+}
+
+/// Adds structured fields and typed attachments to the error of a [Result],
+/// on top of what [Context] does.
+///
+/// ** Example **
+/// ```
+/// use thiserror::Error;
+/// use thiserror_context::{context_fields, impl_context, ContextExt};
+///
+/// #[derive(Debug, Error)]
+/// enum ThisErrorInner {
+///     #[error("placeholder err")]
+///     Placeholder,
+/// }
+/// impl_context!(ThisError(ThisErrorInner));
+///
+/// #[derive(Debug, PartialEq)]
+/// struct HttpStatus(u16);
+///
+/// fn load_user(id: i64) -> Result<(), ThisError> {
+///     Err(ThisErrorInner::Placeholder)
+///         .context_kv("loading user", context_fields!(user_id = id))
+///         .attach(HttpStatus(404))
+/// }
+///
+/// let err = load_user(1).unwrap_err();
+/// assert_eq!(err.fields().count(), 1);
+/// assert_eq!(err.request_ref::<HttpStatus>(), Some(&HttpStatus(404)));
+/// ```
+pub trait ContextExt<W, T, E>
+where
+    E: Into<W>,
+{
+    /// Wrap the error value with additional context along with structured
+    /// key/value fields, usually built with [context_fields].
+    #[track_caller]
+    fn context_kv<C, I, K, V>(self, context: C, fields: I) -> Result<T, W>
+    where
+        C: Display + Send + Sync + 'static,
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<Cow<'static, str>>,
+        V: Into<fields::Value>;
+
+    /// Attach a typed value to the error, which can later be retrieved by
+    /// its type. See [attachments] for more information.
+    #[track_caller]
+    fn attach<A>(self, attachment: A) -> Result<T, W>
+    where
+        A: Send + Sync + 'static;
+}
+
+/// Adds context while naming the wrapper explicitly, for when several
+/// wrappers accept the same error and the return type doesn't pin one.
+///
+/// ** Example **
+/// ```
+/// use thiserror::Error;
+/// use thiserror_context::{impl_context, ContextAs};
+///
+/// #[derive(Debug, Error)]
+/// enum ThisErrorInner {
+///     #[error(transparent)]
+///     ParseInt(#[from] std::num::ParseIntError),
+/// }
+/// impl_context!(ThisError(ThisErrorInner));
+///
+/// #[derive(Debug, Error)]
+/// enum OtherErrorInner {
+///     #[error(transparent)]
+///     ParseInt(#[from] std::num::ParseIntError),
+/// }
+/// impl_context!(OtherError(OtherErrorInner));
+///
+/// let err = "one".parse::<i64>().context_as::<ThisError>("parsing").unwrap_err();
+/// assert_eq!(err.contexts().collect::<Vec<_>>(), ["parsing"]);
+///
+/// let parse = |s: &str| s.parse::<i64>().with_context_as::<OtherError, _>(|| format!("parsing {s}"));
+/// assert_eq!(parse("two").unwrap_err().contexts().next(), Some("parsing two"));
+/// ```
+pub trait ContextAs<T, E> {
+    /// Wrap the error value into `W`, with additional context.
+    #[track_caller]
+    fn context_as<W>(self, context: impl Display + Send + Sync + 'static) -> Result<T, W>
+    where
+        Result<T, E>: Context<W, T, E>,
+        E: Into<W>;
+
+    /// Wrap the error value into `W`, with additional context that is
+    /// evaluated lazily only once an error does occur.
+    #[track_caller]
+    fn with_context_as<W, C>(self, f: impl FnOnce() -> C) -> Result<T, W>
+    where
+        Result<T, E>: Context<W, T, E>,
+        E: Into<W>,
+        C: Display + Send + Sync + 'static;
+}
+
+impl<T, E> ContextAs<T, E> for Result<T, E> {
+    #[track_caller]
+    fn context_as<W>(self, context: impl Display + Send + Sync + 'static) -> Result<T, W>
+    where
+        Result<T, E>: Context<W, T, E>,
+        E: Into<W>,
+    {
+        Context::<W, T, E>::context(self, context)
+    }
+
+    #[track_caller]
+    fn with_context_as<W, C>(self, f: impl FnOnce() -> C) -> Result<T, W>
+    where
+        Result<T, E>: Context<W, T, E>,
+        E: Into<W>,
+        C: Display + Send + Sync + 'static,
+    {
+        Context::<W, T, E>::with_context(self, f)
+    }
+}
+
+/// Turns a `None` into a context enriched error, from the given root error
+/// and with the context frame already added.
+///
+/// ** Example **
+/// ```
+/// use thiserror::Error;
+/// use thiserror_context::{impl_context, OptionContext};
+///
+/// #[derive(Debug, Error)]
+/// enum ThisErrorInner {
+///     #[error("not found")]
+///     NotFound,
+/// }
+/// impl_context!(ThisError(ThisErrorInner));
+///
+/// fn load_user(id: i64) -> Result<String, ThisError> {
+///     let user: Option<String> = None;
+///     user.with_context_or(ThisErrorInner::NotFound, || format!("loading user {id}"))
+/// }
+///
+/// let err = load_user(1).unwrap_err();
+/// assert!(matches!(err.root(), ThisErrorInner::NotFound));
+/// assert_eq!(err.contexts().collect::<Vec<_>>(), ["loading user 1"]);
+/// ```
+pub trait OptionContext<W, T> {
+    /// Wrap a `None` into the given root error, with additional context.
+    #[track_caller]
+    fn context_or<R, C>(self, root: R, context: C) -> Result<T, W>
+    where
+        Result<T, R>: Context<W, T, R>,
+        R: Into<W>,
+        C: Display + Send + Sync + 'static;
+
+    /// Wrap a `None` into the given root error, with additional context that
+    /// is evaluated lazily only if the value is missing.
+    #[track_caller]
+    fn with_context_or<R, C, F>(self, root: R, f: F) -> Result<T, W>
+    where
+        Result<T, R>: Context<W, T, R>,
+        R: Into<W>,
+        C: Display + Send + Sync + 'static,
+        F: FnOnce() -> C;
+}
+
+impl<W, T> OptionContext<W, T> for Option<T> {
+    #[track_caller]
+    fn context_or<R, C>(self, root: R, context: C) -> Result<T, W>
+    where
+        Result<T, R>: Context<W, T, R>,
+        R: Into<W>,
+        C: Display + Send + Sync + 'static,
+    {
+        self.ok_or(root).context(context)
+    }
+
+    #[track_caller]
+    fn with_context_or<R, C, F>(self, root: R, f: F) -> Result<T, W>
+    where
+        Result<T, R>: Context<W, T, R>,
+        R: Into<W>,
+        C: Display + Send + Sync + 'static,
+        F: FnOnce() -> C,
+    {
+        self.ok_or(root).with_context(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use thiserror::Error;
+
+    #[derive(Debug, Error)]
+    pub enum DummyErrorInner {
+        #[error("dummy err msg")]
+        Dummy,
+        #[error("parse int err: {0}")]
+        ParseInt(#[from] std::num::ParseIntError),
+    }
+
+    impl_context!(DummyError(DummyErrorInner));
+
+    fn t() -> Result<(), DummyError> {
+        let _: i64 = "fake".parse()?;
+        Ok(())
+    }
+
+    #[test]
+    #[allow(clippy::useless_format)]
+    fn it_works() {
+        let r = t()
+            .context("first")
+            .with_context::<String, _>(|| format!("second dynamic"))
+            .context("third"); //: Result<(), DummyError> = Err(DummyErrorInner::Dummy.into());
+
+        let res = format!(
+            "{:#?}",
+            r.as_ref()
+                .unwrap_err()
+                .report()
+                .backtrace(false)
+                .locations(false)
+        );
+
+        assert_eq!(
+            res,
+            "ParseInt(ParseIntError { kind: InvalidDigit })\n\nCaused by:\n    0: third\n    1: second dynamic\n    2: first\n\nSources:\n    0: invalid digit found in string\n"
+        );
+    }
+
+    #[test]
+    fn it_works2() {
+        let r = t().context("parsing test").context("second");
+
+        let r = format!(
+            "{:#?}",
+            r.unwrap_err().report().backtrace(false).locations(false)
+        );
+
+        assert_eq!(
+            r,
+            "ParseInt(ParseIntError { kind: InvalidDigit })\n\nCaused by:\n    0: second\n    1: parsing test\n\nSources:\n    0: invalid digit found in string\n",
+        );
+    }
+
+    #[test]
+    fn no_contrext_omits_causation() {
+        let r = t();
+
+        let r = format!(
+            "{:#?}",
+            r.unwrap_err().report().backtrace(false).locations(false)
+        );
+
+        assert_eq!(
+            r,
+            "ParseInt(ParseIntError { kind: InvalidDigit })\n\nSources:\n    0: invalid digit found in string\n",
+        );
+    }
+
+    #[test]
+    fn report_without_sources() {
+        let r = t().context("first").unwrap_err();
+
+        let r = format!(
+            "{:?}",
+            r.report().backtrace(false).sources(false).locations(false)
+        );
+
+        assert_eq!(
+            r,
+            "ParseInt(ParseIntError { kind: InvalidDigit })\n\nCaused by:\n    0: first\n",
+        );
+    }
+
+    #[test]
+    fn no_context_no_sources_is_root_only() {
+        let r = format!(
+            "{:?}",
+            t().unwrap_err()
+                .report()
+                .backtrace(false)
+                .sources(false)
+                .locations(false)
+        );
+
+        assert_eq!(r, "ParseInt(ParseIntError { kind: InvalidDigit })");
+    }
+
+    #[test]
+    fn context_frames_are_sources() {
+        use std::error::Error as _;
+
+        let r = t().context("first").context("second").unwrap_err();
+
+        let chain: Vec<String> = std::iter::successors(r.source(), |&e| e.source())
+            .map(|e| e.to_string())
+            .collect();
+
+        assert_eq!(
+            chain,
+            [
+                "second",
+                "first",
+                "parse int err: invalid digit found in string",
+                "invalid digit found in string",
+            ]
+        );
+    }
+
+    #[test]
+    fn no_context_source_is_root_source() {
+        use std::error::Error as _;
+
+        let r = t().unwrap_err();
+
+        assert_eq!(
+            r.source().map(|e| e.to_string()).as_deref(),
+            Some("invalid digit found in string")
+        );
+    }
+
+    #[test]
+    fn anyhow_sees_context_frames() {
+        let r = anyhow::Error::from(t().context("first").context("second").unwrap_err());
+
+        assert_eq!(
+            format!("{:#}", r),
+            "parse int err: invalid digit found in string: second: first: parse int err: invalid digit found in string: invalid digit found in string",
+        );
+        assert!(r.chain().any(|e| e.is::<DummyErrorInner>()));
+    }
+
+    #[test]
+    fn debug_is_compact() {
+        let r = t().context("first").context("second").unwrap_err();
+        assert_eq!(
+            format!("{r:?}"),
+            r#"DummyError { root: ParseInt(ParseIntError { kind: InvalidDigit }), context: ["second", "first"] }"#
+        );
+        assert_eq!(format!("{r:#?}"), format!("{:?}", r.report()));
+
+        let r = Contextual::new(DummyErrorInner::Dummy);
+        assert_eq!(format!("{r:?}"), "Dummy");
+    }
+
+    #[test]
+    fn renderers() {
+        use crate::render::{HtmlRenderer, MarkdownRenderer, PlainRenderer};
+
+        let r = t()
+            .context_kv("loading <user>", context_fields!(name = "a&b"))
+            .context("handling *request*")
+            .unwrap_err();
+        let report = r
+            .report()
+            .backtrace(false)
+            .locations(false)
+            .backtrace(false);
+
+        assert_eq!(
+            report.render_with(&PlainRenderer).to_string(),
+            format!("{:?}", report)
+        );
+        assert_eq!(r.render_with(&PlainRenderer).to_string(), format!("{r:#?}"));
+        assert_eq!(
+            report.render_with(&MarkdownRenderer).to_string(),
+            "**Error:** ParseInt(ParseIntError { kind: InvalidDigit })\n\n**Caused by:**\n1. handling \\*request\\*\n2. loading \\<user\\> `{name=\"a&b\"}`\n\n**Sources:**\n1. invalid digit found in string\n"
+        );
+        assert_eq!(
+            report.render_with(&HtmlRenderer).to_string(),
+            "<div class=\"error-report\">\n<p class=\"root\">ParseInt(ParseIntError { kind: InvalidDigit })</p>\n<h4>Caused by</h4>\n<ol class=\"context\">\n<li>handling *request*</li>\n<li>loading &lt;user&gt; <span class=\"fields\">{name=&quot;a&amp;b&quot;}</span></li>\n</ol>\n<h4>Sources</h4>\n<ol class=\"sources\">\n<li>invalid digit found in string</li>\n</ol>\n</div>\n"
+        );
+    }
+
+    #[test]
+    fn markdown_keeps_list_items_whole() {
+        use crate::render::MarkdownRenderer;
+
+        let r = t()
+            .context_kv("loading", context_fields!(code = "a`b``c"))
+            .context("handling\n# not a heading\n1. not a list\n- nor this")
+            .unwrap_err();
+        let report = r.report().backtrace(false).locations(false).sources(false);
+
+        assert_eq!(
+            report.render_with(&MarkdownRenderer).to_string(),
+            "**Error:** ParseInt(ParseIntError { kind: InvalidDigit })\n\n**Caused by:**\n1. handling\n   \\# not a heading\n   1\\. not a list\n   \\- nor this\n2. loading ```{code=\"a`b``c\"}```\n"
+        );
+    }
+
+    #[cfg(feature = "color")]
+    #[test]
+    fn color_renderer() {
+        use crate::render::ColorRenderer;
+
+        let r = t().context("first").unwrap_err();
+        let report = r.report().backtrace(false).sources(false);
+        assert_eq!(
+            report.render_with(&ColorRenderer::always()).to_string(),
+            format!(
+                "\x1b[1;31mParseInt(ParseIntError {{ kind: InvalidDigit }})\x1b[0m \x1b[36m({})\x1b[0m\n\n\x1b[1mCaused by:\x1b[0m\n    \x1b[2m0: first\x1b[0m \x1b[36m({})\x1b[0m\n",
+                r.location(),
+                r.0.frames().iter().next().unwrap().location(),
+            )
+        );
+    }
+
+    #[test]
+    fn write_report_to_fixed_buffers() {
+        let r = t().context("first").context("second").unwrap_err();
+        let expected = format!("{:?}", r.report().backtrace(false));
+
+        let mut buf = vec![0u8; expected.len()];
+        let mut w = &mut buf[..];
+        r.write_report(&mut w).unwrap();
+        let written = expected.len() - w.len();
+        assert_eq!(std::str::from_utf8(&buf[..written]).unwrap(), expected);
+
+        let mut small = [0u8; 8];
+        let err = r.write_report(&mut &mut small[..]).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::WriteZero);
+
+        let mut s = String::new();
+        r.write_report_fmt(&mut s).unwrap();
+        assert_eq!(s, expected);
+    }
+
+    #[test]
+    fn backtrace_is_captured_on_conversion() {
+        use std::backtrace::{Backtrace, BacktraceStatus};
+
+        let r = t().context("first").unwrap_err();
+
+        // Capturing honors the same environment variables in both cases.
+        assert_eq!(r.backtrace().status(), Backtrace::capture().status());
+
+        let report = format!("{:#?}", r);
+        assert_eq!(
+            report.contains("Stack backtrace:"),
+            r.backtrace().status() == BacktraceStatus::Captured
+        );
+        assert!(!format!("{:?}", r.report().backtrace(false)).contains("Stack backtrace:"));
+    }
+
+    #[test]
+    fn locations_are_recorded() {
+        use crate::chain::ContextFrame;
+        use std::error::Error as _;
+
+        let line = line!() + 1;
+        let r: Result<(), DummyError> = Err(DummyErrorInner::Dummy).context("first");
+        let r = r.unwrap_err();
+
+        let frame = r.source().unwrap();
+        let frame = frame
+            .downcast_ref::<ContextFrame<DummyErrorInner>>()
+            .unwrap();
+
+        // The root is converted by the same `context` call.
+        assert_eq!(r.location(), frame.location());
+        assert_eq!(frame.location().file(), file!());
+        assert_eq!(frame.location().line(), line);
+        assert_eq!(
+            format!("{:?}", r.report().backtrace(false)),
+            format!(
+                "Dummy ({0})\n\nCaused by:\n    0: first ({0})\n",
+                frame.location()
+            )
+        );
+    }
+
+    #[test]
+    fn question_mark_records_location() {
+        let r = t().unwrap_err();
+
+        assert_eq!(r.location().file(), file!());
+        assert!(format!("{:#?}", r).starts_with(&format!(
+            "ParseInt(ParseIntError {{ kind: InvalidDigit }}) ({})",
+            r.location()
+        )));
+    }
+
+    #[test]
+    fn fields_span_the_whole_chain() {
+        use crate::fields::Value;
+
+        let r = t()
+            .context_kv("first", context_fields!(id = 1u8, name = "a"))
+            .context("second")
+            .context_kv("third", [("retry", true)])
+            .unwrap_err();
+
+        let fields: Vec<_> = r.fields().collect();
+        assert_eq!(
+            fields,
+            [
+                ("retry", &Value::Bool(true)),
+                ("id", &Value::U64(1)),
+                ("name", &Value::Str("a".to_string())),
+            ]
+        );
+        assert_eq!(
+            format!("{:?}", r.report().backtrace(false).sources(false).locations(false)),
+            "ParseInt(ParseIntError { kind: InvalidDigit })\n\nCaused by:\n    0: third {retry=true}\n    1: second\n    2: first {id=1, name=\"a\"}\n",
+        );
+    }
 
-use std::fmt::Display;
+    #[test]
+    fn dynamic_field_keys() {
+        let key = String::from("dynamic");
+        let r = t().context_kv("first", [(key, 1.5)]).unwrap_err();
 
-/// Defines a new struct that wraps the error type, allowing additional
-/// context to be added.
-///
-/// The wrapped error type is intended to be a [thiserror](https://docs.rs/thiserror) enum, but
-/// should work with any error type.
-///
-/// The wrapper will implement all the [From] methods that the wrapped
-/// error type implements.
-///
-/// Ultimately, this allows [anyhow](https://docs.rs/anyhow)-like `context` and `with_context`
-/// calls on a strongly typed error.
-///
-/// ** Example **
-/// ```ignore
-/// impl_context!(DummyError(DummyErrorInner));
-/// ```
-#[macro_export]
-macro_rules! impl_context {
-    ($out:ident($ty:ty)) => {
-        impl<T: Into<$ty>> From<T> for $out {
-            fn from(value: T) -> Self {
-                $out::Base(value.into())
-            }
-        }
+        assert_eq!(
+            r.fields()
+                .map(|(k, v)| format!("{k}={v}"))
+                .collect::<Vec<_>>(),
+            ["dynamic=1.5"]
+        );
+    }
 
-        pub enum $out {
-            Base($ty),
-            Context { error: Box<$out>, context: String },
-        }
+    #[test]
+    fn attachments_are_requested_by_type() {
+        #[derive(Debug, PartialEq)]
+        struct RequestId(&'static str);
 
-        impl $out {
-            pub fn into_inner(self) -> $ty {
-                match self {
-                    $out::Base(b) => b,
-                    $out::Context { error, .. } => error.into_inner(),
-                }
-            }
+        let r = t()
+            .attach(RequestId("root"))
+            .attach(7u16)
+            .context("first")
+            .context("second")
+            .attach(RequestId("second"))
+            .unwrap_err();
 
-            /// Returns all the context messages and the root error.
-            fn all<'a>(&'a self, mut context: Vec<&'a String>) -> (Vec<&'a String>, &'a $ty) {
-                match self {
-                    $out::Base(b) => (context, b),
-                    $out::Context { error, context: c } => {
-                        context.push(c);
-                        error.all(context)
-                    }
-                }
-            }
-        }
+        assert_eq!(r.request_ref::<RequestId>(), Some(&RequestId("second")));
+        assert_eq!(
+            r.request_all::<RequestId>().collect::<Vec<_>>(),
+            [&RequestId("second"), &RequestId("root")]
+        );
+        assert_eq!(r.request_ref::<u16>(), Some(&7));
+        assert_eq!(r.request_ref::<u32>(), None);
 
-        impl std::fmt::Debug for $out {
-            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), core::fmt::Error> {
-                let (context, root) = self.all(vec![]);
-                f.write_fmt(format_args!("{:?}", root))?; //Caused by:\n", root))?;
+        let r = r.attach(RequestId("late"));
+        assert_eq!(r.request_ref::<RequestId>(), Some(&RequestId("late")));
+    }
 
-                if !context.is_empty() {
-                    f.write_fmt(format_args!("\n\nCaused by:\n"))?;
-                }
+    #[test]
+    fn parts_round_trip() {
+        let r: Result<(), DummyError> = Err(DummyErrorInner::Dummy).context("first");
+        let r = r.context("second").unwrap_err();
+        assert_eq!(r.context_len(), 2);
+        assert_eq!(r.contexts().collect::<Vec<_>>(), ["second", "first"]);
 
-                for (i, context) in context.iter().enumerate() {
-                    f.write_fmt(format_args!("    {i}: {}\n", context))?;
-                }
-                Ok(())
-            }
-        }
+        let (root, contexts) = r.into_parts();
+        assert!(matches!(root, DummyErrorInner::Dummy));
+        assert_eq!(contexts, ["second", "first"]);
 
-        impl std::fmt::Display for $out {
-            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-                f.write_fmt(format_args!("{}", self.as_ref()))
-            }
+        let line = line!() + 1;
+        let r = DummyError::from_parts(root, contexts);
+        assert_eq!(r.location().line(), line);
+        assert_eq!(
+            format!("{:?}", r.report().backtrace(false).locations(false)),
+            "Dummy\n\nCaused by:\n    0: second\n    1: first\n"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        #[derive(Debug, Error, serde::Serialize, serde::Deserialize)]
+        pub enum SerdeErrorInner {
+            #[error("not found: {0}")]
+            NotFound(u32),
         }
+        impl_context!(SerdeError(SerdeErrorInner));
 
-        impl std::error::Error for $out {}
+        let r: Result<(), SerdeError> = Err(SerdeErrorInner::NotFound(7)).context("inner");
+        let r = r.context("outer").unwrap_err();
 
-        impl AsRef<$ty> for $out {
-            fn as_ref(&self) -> &$ty {
-                match self {
-                    $out::Base(b) => b,
-                    // One to get out of the Box, and another to
-                    // recursively call this method.
-                    $out::Context { error, .. } => error.as_ref().as_ref(),
-                }
-            }
+        let json = serde_json::to_value(&r).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "version": 1,
+                "error": { "NotFound": 7 },
+                "display": "not found: 7",
+                "context": ["outer", "inner"],
+            })
+        );
+
+        let r: SerdeError = serde_json::from_value(json).unwrap();
+        assert!(matches!(r.root(), SerdeErrorInner::NotFound(7)));
+        assert_eq!(r.contexts().collect::<Vec<_>>(), ["outer", "inner"]);
+
+        let newer = serde_json::json!({ "version": 2, "error": { "NotFound": 7 } });
+        let err = serde_json::from_value::<SerdeError>(newer).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("unsupported error format version 2"));
+    }
+
+    #[test]
+    fn display_chain() {
+        let r = t().context("first").context("second").unwrap_err();
+        assert_eq!(
+            format!("{r}"),
+            "parse int err: invalid digit found in string"
+        );
+        assert_eq!(
+            format!("{r:#}"),
+            "second: first: parse int err: invalid digit found in string"
+        );
+        assert_eq!(
+            r.display_chain().separator(" | ").to_string(),
+            "second | first | parse int err: invalid digit found in string"
+        );
+
+        let r: DummyError = DummyErrorInner::Dummy.into();
+        assert_eq!(format!("{r:#}"), "dummy err msg");
+    }
+
+    #[test]
+    fn iterator_context() {
+        use crate::fields::Value;
+
+        let items = ["1", "x", "3", "y"].into_iter().map(str::parse::<u8>);
+        let r: Vec<Result<u8, DummyError>> = items.context("parsing").with_index().collect();
+        assert!(matches!(r[0], Ok(1)));
+        let err = r[3].as_ref().unwrap_err();
+        assert_eq!(err.contexts().collect::<Vec<_>>(), ["parsing"]);
+        assert_eq!(
+            err.fields().collect::<Vec<_>>(),
+            [("index", &Value::U64(3))]
+        );
+
+        let items = ["1", "x"].into_iter().map(str::parse::<u8>);
+        let line = line!() + 1;
+        let items = items.with_context(|idx| format!("item {idx}"));
+        let r: Result<Vec<u8>, DummyError> = items.collect();
+        let err = r.unwrap_err();
+        assert_eq!(err.contexts().collect::<Vec<_>>(), ["item 1"]);
+        assert_eq!(err.fields().count(), 0);
+        assert_eq!(
+            err.0.frames().iter().next().unwrap().location().line(),
+            line
+        );
+
+        // The adapters are `Clone` even though the wrapper isn't.
+        let items = ["1", "x"].into_iter().map(str::parse::<u8>);
+        let items = items.with_context(|idx| format!("item {idx}")).with_index();
+        let r: Result<Vec<u8>, DummyError> = items.clone().collect();
+        let s: Result<Vec<u8>, DummyError> = items.collect();
+        assert_eq!(r.unwrap_err().to_string(), s.unwrap_err().to_string());
+    }
+
+    #[cfg(feature = "futures")]
+    #[tokio::test]
+    async fn stream_context() {
+        use crate::fields::Value;
+        use futures_util::{stream, StreamExt};
+
+        let items = stream::iter(["1", "x"]).map(str::parse::<u8>);
+        let r: Vec<Result<u8, DummyError>> = items
+            .with_context(|idx| format!("item {idx}"))
+            .with_index()
+            .collect()
+            .await;
+        assert!(matches!(r[0], Ok(1)));
+        let err = r[1].as_ref().unwrap_err();
+        assert_eq!(err.contexts().collect::<Vec<_>>(), ["item 1"]);
+        assert_eq!(
+            err.fields().collect::<Vec<_>>(),
+            [("index", &Value::U64(1))]
+        );
+
+        let items = stream::iter(["1", "x"].map(str::parse::<u8>));
+        let items = items.context("parsing");
+        let r: Vec<Result<u8, DummyError>> = items.clone().collect().await;
+        let s: Vec<Result<u8, DummyError>> = items.collect().await;
+        assert_eq!(r.len(), s.len());
+    }
+
+    #[cfg(feature = "futures")]
+    #[tokio::test]
+    async fn future_context() {
+        async fn fail() -> Result<(), DummyErrorInner> {
+            Err(DummyErrorInner::Dummy)
         }
 
-        impl<Z, E: Into<$out>> Context<$out, Z, E> for Result<Z, E> {
-            fn context<C>(self, context: C) -> Result<Z, $out>
-            where
-                C: std::fmt::Display + Send + Sync + 'static,
-            {
-                match self {
-                    Ok(t) => Ok(t),
-                    Err(e) => {
-                        let out: $out = e.into();
-                        Err($out::Context {
-                            error: Box::new(out),
-                            context: context.to_string(),
-                        })
-                    }
-                }
-            }
+        let line = line!() + 1;
+        let fut = fail().context::<DummyError, _, _>("inner");
+        let r: Result<(), DummyError> = fut.with_context(|| "outer").await;
+        let r = r.unwrap_err();
+        assert_eq!(r.contexts().collect::<Vec<_>>(), ["outer", "inner"]);
+        let lines: Vec<_> = r.0.frames().iter().map(|f| f.location().line()).collect();
+        assert_eq!(lines, [line + 1, line]);
 
-            fn with_context<C, F>(self, f: F) -> Result<Z, $out>
-            where
-                C: std::fmt::Display + Send + Sync + 'static,
-                F: FnOnce() -> C,
-            {
-                match self {
-                    Ok(t) => Ok(t),
-                    Err(e) => {
-                        let out: $out = e.into();
-                        Err($out::Context {
-                            error: Box::new(out),
-                            context: format!("{}", f()),
-                        })
-                    }
-                }
-            }
+        // The adapters don't prevent the future from being spawned.
+        let r = tokio::spawn(fail().context::<DummyError, _, _>("spawned")).await;
+        assert_eq!(r.unwrap().unwrap_err().contexts().next(), Some("spawned"));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn tokio_errors() {
+        use crate::future::{ElapsedContext, JoinErrorContext};
+        use std::time::Duration;
+
+        #[derive(Debug, Error)]
+        pub enum TaskErrorInner {
+            #[error("task failed")]
+            Join(#[source] tokio::task::JoinError),
+            #[error("timed out")]
+            Timeout(#[source] tokio::time::error::Elapsed),
         }
-    };
-}
+        impl_context!(TaskError(TaskErrorInner));
 
-pub trait Context<W, T, E>
-where
-    E: Into<W>,
-{
-    /// Wrap the error value with additional context.
-    fn context<C>(self, context: C) -> Result<T, W>
-    where
-        C: Display + Send + Sync + 'static;
+        let handle = tokio::spawn(async { std::panic::resume_unwind(Box::new(())) });
+        let r: Result<(), TaskError> = handle.await.task_context(TaskErrorInner::Join, "sync");
+        let r = r.unwrap_err();
+        assert!(matches!(r.root(), TaskErrorInner::Join(e) if e.is_panic()));
+        assert_eq!(r.contexts().collect::<Vec<_>>(), ["joining task sync"]);
 
-    /// Wrap the error value with additional context that is evaluated lazily
-    /// only once an error does occur.
-    fn with_context<C, F>(self, f: F) -> Result<T, W>
-    where
-        C: Display + Send + Sync + 'static,
-        F: FnOnce() -> C;
-}
+        let timeout = Duration::from_millis(1);
+        let r: Result<(), TaskError> = tokio::time::timeout(timeout, std::future::pending())
+            .await
+            .timeout_context(TaskErrorInner::Timeout, timeout);
+        let r = r.unwrap_err();
+        assert!(matches!(r.root(), TaskErrorInner::Timeout(_)));
+        assert_eq!(r.contexts().collect::<Vec<_>>(), ["timed out after 1ms"]);
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use thiserror::Error;
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_report() {
+        let r = t()
+            .context_kv("first", context_fields!(id = 1u8, retry = true))
+            .context("second")
+            .unwrap_err();
+        let frames: Vec<_> = r.0.frames().iter().map(|f| f.location()).collect();
 
-    #[derive(Debug, Error)]
-    pub enum DummyErrorInner {
-        #[error("dummy err msg")]
-        Dummy,
-        #[error("parse int err: {0}")]
-        ParseInt(#[from] std::num::ParseIntError),
+        assert_eq!(
+            r.to_json_report(),
+            serde_json::json!({
+                "type": "thiserror_context::tests::DummyErrorInner",
+                "display": "parse int err: invalid digit found in string",
+                "debug": "ParseInt(ParseIntError { kind: InvalidDigit })",
+                "location": r.location().to_string(),
+                "context": [
+                    { "message": "second", "location": frames[0].to_string(), "fields": {} },
+                    {
+                        "message": "first",
+                        "location": frames[1].to_string(),
+                        "fields": { "id": 1, "retry": true },
+                    },
+                ],
+                "sources": ["invalid digit found in string"],
+            })
+        );
     }
 
-    impl_context!(DummyError(DummyErrorInner));
+    #[cfg(feature = "anyhow")]
+    #[test]
+    fn anyhow_round_trip() {
+        let r: Result<(), DummyError> = Err(DummyErrorInner::Dummy).context("inner");
+        let r = r.context("outer").unwrap_err();
+        let expected = anyhow::Error::new(DummyErrorInner::Dummy)
+            .context("inner")
+            .context("outer");
+        assert_eq!(format!("{:#}", r.into_anyhow()), format!("{:#}", expected));
 
-    fn t() -> Result<(), DummyError> {
-        let _: i64 = "fake".parse()?;
-        Ok(())
+        // A wrapper converted with `?` keeps its frames under anyhow's.
+        let r: Result<(), DummyError> = Err(DummyErrorInner::Dummy).context("inner");
+        let r = anyhow::Error::new(r.unwrap_err()).context("outer");
+        let r = DummyError::try_from_anyhow(r).unwrap();
+        assert_eq!(r.contexts().collect::<Vec<_>>(), ["outer", "inner"]);
+        assert!(matches!(r.into_inner(), DummyErrorInner::Dummy));
     }
 
     #[test]
-    fn it_works() {
-        let r = t()
-            .context("first")
-            .with_context::<String, _>(|| format!("second dynamic"))
-            .context("third"); //: Result<(), DummyError> = Err(DummyErrorInner::Dummy.into());
+    fn option_context() {
+        let line = line!() + 1;
+        let r: Result<u8, DummyError> = None.context_or(DummyErrorInner::Dummy, "loading");
+        let r = r.unwrap_err();
+        assert!(matches!(r.root(), DummyErrorInner::Dummy));
+        assert_eq!(r.contexts().collect::<Vec<_>>(), ["loading"]);
+        assert_eq!(r.location().line(), line);
+
+        let r: Result<u8, DummyError> =
+            Some(1).with_context_or(DummyErrorInner::Dummy, || -> String { unreachable!() });
+        assert_eq!(r.unwrap(), 1);
+    }
+
+    #[test]
+    fn contextual_is_shared_by_wrappers() {
+        use crate::contextual::Wrapper;
+
+        fn first_context<E>(err: &Contextual<E>) -> Option<String> {
+            err.contexts().next().map(str::to_owned)
+        }
 
-        let res = format!("{:#?}", r.as_ref().unwrap_err());
+        let r: Result<(), DummyError> = Err(DummyErrorInner::Dummy).context("first");
+        let r = r.unwrap_err();
+        assert_eq!(first_context(r.as_ref()), Some("first".to_string()));
 
+        // Converting back and forth keeps the frames.
+        let contextual = r.into_contextual().add_context("second");
         assert_eq!(
-            res,
-            "ParseInt(ParseIntError { kind: InvalidDigit })\n\nCaused by:\n    0: third\n    1: second dynamic\n    2: first\n"
+            format!(
+                "{:?}",
+                contextual.report().backtrace(false).locations(false)
+            ),
+            "Dummy\n\nCaused by:\n    0: second\n    1: first\n"
         );
+        let r = DummyError::from_contextual(contextual);
+        assert_eq!(r.context_len(), 2);
+
+        let r: Contextual<std::num::ParseIntError> = "fake".parse::<i64>().unwrap_err().into();
+        assert_eq!(first_context(&r), None);
+        assert_eq!(r.to_string(), "invalid digit found in string");
     }
 
     #[test]
-    fn it_works2() {
-        let r = t().context("parsing test").context("second");
+    fn deep_chains_are_flat() {
+        use std::error::Error as _;
+
+        let mut r: Result<(), DummyError> = Err(DummyErrorInner::Dummy.into());
+        for i in 0..100_000 {
+            r = r.context(i);
+        }
+        let r = r.unwrap_err();
+
+        let sources = std::iter::successors(r.source(), |&e| e.source());
+        assert_eq!(sources.count(), 100_001);
+        assert!(matches!(r.into_inner(), DummyErrorInner::Dummy));
+    }
 
-        let r = format!("{:#?}", r.unwrap_err());
+    // The chain is linked through raw pointers, so this is meant to run
+    // under Miri as well: `cargo +nightly miri test source_chain_follows_moves`.
+    #[test]
+    fn source_chain_follows_moves() {
+        #[derive(Debug, Error)]
+        #[error("mapped")]
+        pub struct MappedInner(#[source] DummyErrorInner);
+        impl_context!(Mapped(MappedInner));
+
+        fn chain(e: &dyn std::error::Error) -> Vec<String> {
+            std::iter::successors(e.source(), |&e| e.source())
+                .map(|e| e.to_string())
+                .collect()
+        }
 
+        let r = t().context("first").context("second").unwrap_err();
+        let before = chain(&r);
+
+        let moved = Box::new(r);
+        assert_eq!(chain(&*moved), before);
+
+        // Enough frames to reallocate the list a few times between walks.
+        let mut r = Err::<(), _>(*moved).context("third");
+        for i in 0..20 {
+            r = r.context(i);
+            assert_eq!(chain(r.as_ref().unwrap_err()).len(), i + 6);
+        }
+        let r = r.unwrap_err();
         assert_eq!(
-            r,
-            "ParseInt(ParseIntError { kind: InvalidDigit })\n\nCaused by:\n    0: second\n    1: parsing test\n",
+            chain(&r)[19..],
+            [
+                "0",
+                "third",
+                "second",
+                "first",
+                "parse int err: invalid digit found in string",
+                "invalid digit found in string"
+            ]
         );
+
+        // Concurrent walks link the same frames to the same places.
+        std::thread::scope(|s| {
+            let walks = [(); 2].map(|_| s.spawn(|| chain(&r)));
+            for walk in walks {
+                assert_eq!(walk.join().unwrap(), chain(&r));
+            }
+        });
+
+        let r: Mapped = r.map_root(MappedInner);
+        assert_eq!(
+            chain(&r)[19..],
+            [
+                "0",
+                "third",
+                "second",
+                "first",
+                "mapped",
+                "parse int err: invalid digit found in string",
+                "invalid digit found in string"
+            ]
+        );
+
+        let (root, contexts) = r.into_parts();
+        let r = Mapped::from_parts(root, contexts);
+        assert_eq!(chain(&r).len(), 26);
+        let r = Err::<(), _>(r).context("last").unwrap_err();
+        assert_eq!(chain(&r)[..2], ["last", "19"]);
     }
 
+    // Like any `pub` item exposing a private type, the wrapper's methods warn,
+    // but they compile.
     #[test]
-    fn no_contrext_omits_causation() {
-        let r = t();
+    #[allow(private_bounds, private_interfaces)]
+    fn private_roots() {
+        #[derive(Debug, Error)]
+        #[error("private err")]
+        struct PrivateErrorInner;
+        impl_context!(PrivateError(PrivateErrorInner));
+
+        let r: Result<(), PrivateError> = Err(PrivateErrorInner).context("private");
+        let (root, contexts) = r.unwrap_err().into_parts();
+        assert_eq!(contexts, ["private"]);
+
+        let r = PrivateError::from_parts(root, contexts).attach(1u8);
+        assert_eq!(r.request_ref::<u8>(), Some(&1));
+        assert!(matches!(r.into_inner(), PrivateErrorInner));
+    }
+
+    #[test]
+    fn context_only_requires_two_methods() {
+        #[derive(Debug)]
+        struct Custom(String);
+
+        impl<T> Context<Custom, T, Custom> for Result<T, Custom> {
+            fn context<C: Display + Send + Sync + 'static>(self, context: C) -> Result<T, Custom> {
+                self.map_err(|e| Custom(format!("{context}: {}", e.0)))
+            }
 
-        let r = format!("{:#?}", r.unwrap_err());
+            fn with_context<C, F>(self, f: F) -> Result<T, Custom>
+            where
+                C: Display + Send + Sync + 'static,
+                F: FnOnce() -> C,
+            {
+                self.map_err(|e| Custom(format!("{}: {}", f(), e.0)))
+            }
+        }
+
+        let r: Result<(), Custom> = Err(Custom("root".to_string())).context("outer");
+        assert_eq!(r.unwrap_err().0, "outer: root");
+    }
+
+    #[test]
+    fn send_roots_stay_send() {
+        #[derive(Debug, Error)]
+        #[error("cell err")]
+        pub struct CellErrorInner(std::cell::Cell<u8>);
+        impl_context!(CellError(CellErrorInner));
 
-        assert_eq!(r, "ParseInt(ParseIntError { kind: InvalidDigit })",);
+        fn assert_send<T: Send>() {}
+        assert_send::<CellError>();
     }
 
     #[test]
@@ -463,6 +1986,20 @@ mod tests {
         assert!(v()
             .context("Adding context shouldn't cause build error")
             .is_err());
+
+        // Without a return type to pin the wrapper, it is named explicitly.
+        let r = "fake"
+            .parse::<i64>()
+            .context_as::<AnotherDummyError>("parsing");
+        assert_eq!(r.unwrap_err().contexts().next(), Some("parsing"));
+        let parse = |s: &str| {
+            s.parse::<i64>()
+                .with_context_as::<DummyError, _>(|| format!("parsing {s}"))
+        };
+        assert!(matches!(
+            parse("fake").unwrap_err().root(),
+            DummyErrorInner::ParseInt(_)
+        ));
     }
 }
 
@@ -485,6 +2022,7 @@ mod composable_tests {
         }
         impl_context!(DummyError(DummyErrorInner));
 
+        #[allow(clippy::useless_conversion)]
         pub fn t() -> Result<(), DummyError> {
             Err::<(), DummyErrorInner>(DummyErrorInner::Dummy.into())
                 .context("first")
@@ -515,11 +2053,146 @@ mod composable_tests {
     fn it_is_composable() {
         let r = wrapped();
 
-        let r = format!("{:#?}", r.unwrap_err());
+        let r = r.unwrap_err();
+        // The inner error is formatted through its own compact `Debug`, its
+        // frames having moved to the outer one.
+        let root = "T(Dummy)".to_string();
+
+        let r = format!("{:#?}", r.report().backtrace(false).locations(false));
 
         assert_eq!(
             r,
-            "T(Dummy)\n\nCaused by:\n    0: fourth\n    1: third\n    2: second\n    3: first\n",
+            root + "\n\nCaused by:\n    0: fourth\n    1: third\n    2: second\n    3: first\n",
+        );
+    }
+    #[test]
+    fn fields_are_carried() {
+        let r: Result<(), Other> = inner::t().context_kv("third", context_fields!(n = 3));
+
+        assert_eq!(
+            r.unwrap_err().fields().map(|(k, _)| k).collect::<Vec<_>>(),
+            ["n"]
+        );
+    }
+
+    #[test]
+    fn attachments_are_carried() {
+        let r: Result<(), DummyError> = inner::t().attach(1u8);
+        let r: Result<(), Other> = r.context("third");
+        let r: Result<(), Other> = r.attach(3u8);
+
+        assert_eq!(
+            r.unwrap_err().request_all::<u8>().collect::<Vec<_>>(),
+            [&3, &1]
+        );
+    }
+
+    #[test]
+    fn root_is_mapped() {
+        #[derive(Debug, Error)]
+        pub enum MappedInner {
+            #[error("mapped")]
+            Mapped,
+        }
+        impl_context!(Mapped(MappedInner));
+
+        let r: Result<(), DummyError> = inner::t().attach(1u8);
+        let r = r.unwrap_err();
+        let location = r.location();
+        let r: Mapped = r.map_root(|_| MappedInner::Mapped);
+        assert!(matches!(r.root(), MappedInner::Mapped));
+        assert_eq!(r.contexts().collect::<Vec<_>>(), ["second", "first"]);
+        assert_eq!(r.request_ref::<u8>(), Some(&1));
+        assert_eq!(r.location(), location);
+
+        let r = inner::t().unwrap_err();
+        let r = r
+            .try_map_root::<Mapped, _>(|root| match root {
+                inner::DummyErrorInner::ParseInt(_) => Ok(MappedInner::Mapped),
+                other => Err(other),
+            })
+            .unwrap_err();
+        assert!(matches!(r.root(), inner::DummyErrorInner::Dummy));
+        assert_eq!(r.contexts().collect::<Vec<_>>(), ["second", "first"]);
+    }
+
+    #[test]
+    fn backtrace_is_carried() {
+        use std::backtrace::Backtrace;
+
+        let r = wrapped().unwrap_err();
+
+        assert_eq!(r.backtrace().status(), Backtrace::capture().status());
+    }
+}
+
+#[cfg(test)]
+// `AppError` stores a whole `StoreError` as its root.
+#[allow(clippy::result_large_err)]
+mod generic_tests {
+    use super::*;
+    use std::fmt::Debug;
+    use thiserror::Error;
+
+    // `E` and `K` are also the names of generic parameters in the generated
+    // impls, so they must not clash.
+    #[derive(Debug, Error)]
+    pub enum StoreErrorInner<K: Debug, E> {
+        #[error("key not found: {0:?}")]
+        NotFound(K),
+        #[error("backend failed")]
+        Backend(#[source] E),
+    }
+    impl_context!(StoreError<K, E>(StoreErrorInner<K, E>) where K: Debug);
+
+    #[derive(Debug, Error)]
+    #[error("invalid token {0:?}")]
+    pub struct ParseErrorInner<'a>(&'a str);
+    impl_context!(ParseError<'a>(ParseErrorInner<'a>));
+
+    impl_context!(AppError(StoreError<u32, std::io::Error>));
+
+    fn get(key: u32) -> Result<(), StoreError<u32, std::io::Error>> {
+        Err(StoreErrorInner::NotFound(key)).context(format!("getting {key}"))
+    }
+
+    fn parse(input: &str) -> Result<(), ParseError<'_>> {
+        Err(ParseErrorInner(input)).context("parsing")
+    }
+
+    #[test]
+    fn generic_wrappers() {
+        let r: StoreError<_, _> = get(1).context("loading").unwrap_err();
+        assert!(matches!(r.as_ref(), StoreErrorInner::NotFound(1)));
+        assert_eq!(r.contexts().collect::<Vec<_>>(), ["loading", "getting 1"]);
+        assert_eq!(
+            format!("{:?}", r.report().backtrace(false).locations(false)),
+            "NotFound(1)\n\nCaused by:\n    0: loading\n    1: getting 1\n"
+        );
+
+        let r: StoreError<u32, std::io::Error> =
+            StoreErrorInner::Backend(std::io::Error::other("disk")).into();
+        assert_eq!(std::error::Error::source(&r).unwrap().to_string(), "disk");
+
+        let r: Result<(), AppError> = get(2).context("in app");
+        assert_eq!(r.unwrap_err().contexts().collect::<Vec<_>>(), ["in app"]);
+    }
+
+    #[test]
+    fn lifetime_wrappers() {
+        let input = String::from("}");
+        let r = parse(&input).unwrap_err();
+        assert_eq!(r.to_string(), "invalid token \"}\"");
+        assert_eq!(r.contexts().collect::<Vec<_>>(), ["parsing"]);
+        assert_eq!(
+            format!("{r:?}"),
+            r#"ParseError { root: ParseErrorInner("}"), context: ["parsing"] }"#
+        );
+        assert_eq!(
+            format!("{:?}", r.report().backtrace(false).locations(false)),
+            "ParseErrorInner(\"}\")\n\nCaused by:\n    0: parsing\n"
         );
+        assert!(format!("{r:#?}").starts_with("ParseErrorInner(\"}\")"));
+        assert_eq!(r.into_inner().0, "}");
     }
 }
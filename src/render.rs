@@ -0,0 +1,613 @@
+//! Pluggable renderers turning a [Report](crate::report::Report) into text.
+//!
+//! A [ReportRenderer] receives a [ReportView] of the error, with the root
+//! error, the context frames in the order chosen by the
+//! [report hook](crate::hook) and the root's source chain, and writes it to
+//! any [fmt::Write]. The crate ships three of them:
+//!
+//! - [PlainRenderer], the `{:#?}` report.
+//! - [MarkdownRenderer], for tickets and chat bots.
+//! - [HtmlRenderer], for web pages, with every message escaped.
+//!
+//! With the `color` feature, `ColorRenderer` also prints the `{:#?}` report
+//! with ANSI colors, for terminals.
+//!
+//! Apply one with the wrapper's `render_with` method, or with
+//! [Report::render_with](crate::report::Report::render_with) to also choose what the view includes.
+//!
+//! ** Example **
+//! ```
+//! use thiserror::Error;
+//! use thiserror_context::render::{HtmlRenderer, MarkdownRenderer};
+//! use thiserror_context::{Context, impl_context};
+//!
+//! #[derive(Debug, Error)]
+//! enum ThisErrorInner {
+//!     #[error("placeholder err")]
+//!     Placeholder,
+//! }
+//! impl_context!(ThisError(ThisErrorInner));
+//!
+//! let err = Err::<(), _>(ThisErrorInner::Placeholder)
+//!     .context("loading <users>")
+//!     .unwrap_err();
+//!
+//! assert_eq!(err.report().backtrace(false).locations(false).render_with(&MarkdownRenderer).to_string(), r#"**Error:** Placeholder
+//!
+//! **Caused by:**
+//! 1. loading \<users\>
+//! "#);
+//!
+//! assert_eq!(err.report().backtrace(false).locations(false).render_with(&HtmlRenderer).to_string(), r#"<div class="error-report">
+//! <p class="root">Placeholder</p>
+//! <h4>Caused by</h4>
+//! <ol class="context">
+//! <li>loading &lt;users&gt;</li>
+//! </ol>
+//! </div>
+//! "#);
+//! ```
+use crate::fields::write_fields;
+use crate::frame::Frame;
+use crate::hook::{Order, ReportHandler, RootFormat};
+use crate::stack::Frames;
+use std::backtrace::{Backtrace, BacktraceStatus};
+use std::error::Error;
+use std::fmt::{self, Display, Write};
+use std::panic::Location;
+
+/// Writes a [ReportView] as text.
+pub trait ReportRenderer {
+    fn render(&self, f: &mut dyn fmt::Write, report: &ReportView<'_>) -> fmt::Result;
+}
+
+impl<R: ReportRenderer + ?Sized> ReportRenderer for &R {
+    fn render(&self, f: &mut dyn fmt::Write, report: &ReportView<'_>) -> fmt::Result {
+        (**self).render(f, report)
+    }
+}
+
+/// Everything a [ReportRenderer] can print, as selected by the
+/// [Report](crate::report::Report) it was built from.
+pub struct ReportView<'a> {
+    pub(crate) root: &'a (dyn Error + 'a),
+    pub(crate) location: &'static Location<'static>,
+    pub(crate) frames: &'a Frames,
+    pub(crate) backtrace: &'a Backtrace,
+    pub(crate) handler: &'static dyn ReportHandler,
+    pub(crate) sources: bool,
+    pub(crate) show_backtrace: bool,
+    pub(crate) locations: bool,
+}
+
+impl<'a> ReportView<'a> {
+    /// The root error.
+    pub fn root(&self) -> &'a (dyn Error + 'a) {
+        self.root
+    }
+
+    /// Writes the root error as chosen by the installed
+    /// [ReportHandler::root_format].
+    pub fn write_root(&self, f: &mut dyn fmt::Write) -> fmt::Result {
+        match self.handler.root_format() {
+            RootFormat::Debug => write!(f, "{:?}", self.root),
+            RootFormat::Display => write!(f, "{}", self.root),
+        }
+    }
+
+    /// Where the root error was converted into the wrapper, unless locations
+    /// were turned off.
+    pub fn location(&self) -> Option<&'static Location<'static>> {
+        self.locations.then_some(self.location)
+    }
+
+    /// Where a context frame was added, unless locations were turned off.
+    pub fn frame_location(&self, frame: &Frame) -> Option<&'static Location<'static>> {
+        self.locations.then_some(frame.location())
+    }
+
+    /// The context frames, in the order chosen by the installed
+    /// [ReportHandler::order].
+    pub fn frames(&self) -> impl ExactSizeIterator<Item = &'a Frame> {
+        InOrder {
+            iter: self.frames.iter(),
+            reverse: self.handler.order() == Order::InnermostFirst,
+        }
+    }
+
+    /// The source chain of the root error, unless sources were turned off.
+    pub fn sources(&self) -> impl Iterator<Item = &'a (dyn Error + 'static)> {
+        let first = if self.sources {
+            self.root.source()
+        } else {
+            None
+        };
+        std::iter::successors(first, |&e| e.source())
+    }
+
+    /// The backtrace captured along with the root error, if one was captured
+    /// and backtraces weren't turned off.
+    pub fn backtrace(&self) -> Option<&'a Backtrace> {
+        let captured = self.backtrace.status() == BacktraceStatus::Captured;
+        (self.show_backtrace && captured).then_some(self.backtrace)
+    }
+
+    /// The installed [ReportHandler], for renderers following its layout.
+    pub fn handler(&self) -> &'static dyn ReportHandler {
+        self.handler
+    }
+}
+
+/// Formats a report with a [ReportRenderer] through [Display], as returned
+/// by [Report::render_with](crate::report::Report::render_with).
+pub struct Rendered<'a, R: ?Sized> {
+    view: ReportView<'a>,
+    renderer: &'a R,
+}
+
+impl<'a, R: ReportRenderer + ?Sized> Rendered<'a, R> {
+    #[doc(hidden)]
+    pub fn new(view: ReportView<'a>, renderer: &'a R) -> Self {
+        Rendered { view, renderer }
+    }
+}
+
+impl<R: ReportRenderer + ?Sized> Display for Rendered<'_, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.renderer.render(f, &self.view)
+    }
+}
+
+/// The plain text report printed by `{:#?}`, laid out by the installed
+/// [ReportHandler].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PlainRenderer;
+
+impl ReportRenderer for PlainRenderer {
+    fn render(&self, f: &mut dyn fmt::Write, report: &ReportView<'_>) -> fmt::Result {
+        render_text(f, report, &Style::PLAIN)
+    }
+}
+
+/// The escape sequences written around each part of a text report.
+struct Style {
+    root: &'static str,
+    frame: &'static str,
+    location: &'static str,
+    heading: &'static str,
+    reset: &'static str,
+}
+
+impl Style {
+    const PLAIN: Style = Style {
+        root: "",
+        frame: "",
+        location: "",
+        heading: "",
+        reset: "",
+    };
+
+    #[cfg(feature = "color")]
+    const ANSI: Style = Style {
+        root: "\x1b[1;31m",
+        frame: "\x1b[2m",
+        location: "\x1b[36m",
+        heading: "\x1b[1m",
+        reset: "\x1b[0m",
+    };
+}
+
+/// Writes the `{:#?}` report, with each part wrapped in the given style.
+fn render_text(f: &mut dyn fmt::Write, report: &ReportView<'_>, style: &Style) -> fmt::Result {
+    let handler = report.handler();
+    let Style {
+        root,
+        frame: dim,
+        location: loc,
+        heading,
+        reset,
+    } = style;
+
+    f.write_str(root)?;
+    report.write_root(f)?;
+    f.write_str(reset)?;
+    if let Some(location) = report.location() {
+        write!(f, " {loc}({location}){reset}")?;
+    }
+
+    let frames = report.frames();
+    let has_frames = frames.len() > 0;
+
+    if has_frames {
+        write!(f, "\n\n{heading}Caused by:{reset}\n")?;
+    }
+
+    for (i, frame) in frames.enumerate() {
+        write!(f, "{}{dim}", handler.indent())?;
+        handler.number(f, i)?;
+        f.write_str(frame.context())?;
+        if !frame.fields().is_empty() {
+            f.write_char(' ')?;
+            write_fields(f, frame.fields())?;
+        }
+        f.write_str(reset)?;
+        if let Some(location) = report.frame_location(frame) {
+            write!(f, " {loc}({location}){reset}")?;
+        }
+        f.write_char('\n')?;
+    }
+
+    // Every section after the root ends with a newline, so the next one
+    // only needs a single blank line in between.
+    let mut separator = if has_frames { "\n" } else { "\n\n" };
+
+    let mut sources = report.sources().peekable();
+    if sources.peek().is_some() {
+        writeln!(f, "{separator}{heading}Sources:{reset}")?;
+        separator = "\n";
+    }
+
+    for (i, source) in sources.enumerate() {
+        write!(f, "{}{dim}", handler.indent())?;
+        handler.number(f, i)?;
+        writeln!(f, "{source}{reset}")?;
+    }
+
+    if let Some(backtrace) = report.backtrace() {
+        write!(
+            f,
+            "{separator}{heading}Stack backtrace:{reset}\n{backtrace}"
+        )?;
+    }
+
+    Ok(())
+}
+
+/// The `{:#?}` report with ANSI colors, behind the `color` feature: the root
+/// error in bold red, the context frames and sources dimmed, and locations
+/// in cyan.
+///
+/// [ColorRenderer::auto] only uses colors when `NO_COLOR` isn't set and
+/// standard error is a terminal, and renders the plain report otherwise.
+///
+/// ** Example **
+/// ```no_run
+/// use thiserror::Error;
+/// use thiserror_context::render::ColorRenderer;
+/// use thiserror_context::{Context, impl_context};
+///
+/// #[derive(Debug, Error)]
+/// enum ThisErrorInner {
+///     #[error("placeholder err")]
+///     Placeholder,
+/// }
+/// impl_context!(ThisError(ThisErrorInner));
+///
+/// fn run() -> Result<(), ThisError> {
+///     Err(ThisErrorInner::Placeholder).context("running")
+/// }
+///
+/// if let Err(err) = run() {
+///     eprintln!("{}", err.render_with(&ColorRenderer::auto()));
+///     std::process::exit(1);
+/// }
+/// ```
+#[cfg(feature = "color")]
+#[derive(Debug, Clone, Copy)]
+pub struct ColorRenderer {
+    enabled: bool,
+}
+
+#[cfg(feature = "color")]
+impl ColorRenderer {
+    /// Uses colors unless `NO_COLOR` is set to a non-empty value or standard
+    /// error isn't a terminal.
+    pub fn auto() -> Self {
+        use std::io::IsTerminal;
+
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        ColorRenderer {
+            enabled: !no_color && std::io::stderr().is_terminal(),
+        }
+    }
+
+    /// Always uses colors.
+    pub fn always() -> Self {
+        ColorRenderer { enabled: true }
+    }
+
+    /// Whether this renderer writes colors.
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+#[cfg(feature = "color")]
+impl Default for ColorRenderer {
+    fn default() -> Self {
+        ColorRenderer::auto()
+    }
+}
+
+#[cfg(feature = "color")]
+impl ReportRenderer for ColorRenderer {
+    fn render(&self, f: &mut dyn fmt::Write, report: &ReportView<'_>) -> fmt::Result {
+        match self.enabled {
+            true => render_text(f, report, &Style::ANSI),
+            false => render_text(f, report, &Style::PLAIN),
+        }
+    }
+}
+
+/// A Markdown report, with each section as a numbered list and the backtrace
+/// in a code block. Messages are escaped so they render as written.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MarkdownRenderer;
+
+impl ReportRenderer for MarkdownRenderer {
+    fn render(&self, f: &mut dyn fmt::Write, report: &ReportView<'_>) -> fmt::Result {
+        f.write_str("**Error:** ")?;
+        report.write_root(&mut MarkdownEscape::new(&mut *f, 0))?;
+        if let Some(location) = report.location() {
+            write!(f, " (`{location}`)")?;
+        }
+        f.write_char('\n')?;
+
+        let mut frames = report.frames().peekable();
+        if frames.peek().is_some() {
+            f.write_str("\n**Caused by:**\n")?;
+        }
+        for (i, frame) in frames.enumerate() {
+            let indent = write_list_marker(f, i + 1)?;
+            MarkdownEscape::new(&mut *f, indent).write_str(frame.context())?;
+            if !frame.fields().is_empty() {
+                // A fence longer than any run of backticks in the fields keeps
+                // them in one code span. They start with `{` and end with `}`,
+                // so the fence never needs padding.
+                let mut runs = BacktickRuns::default();
+                write_fields(&mut runs, frame.fields())?;
+                f.write_char(' ')?;
+                write_backticks(f, runs.longest + 1)?;
+                write_fields(&mut CodeSpan(&mut *f), frame.fields())?;
+                write_backticks(f, runs.longest + 1)?;
+            }
+            if let Some(location) = report.frame_location(frame) {
+                write!(f, " (`{location}`)")?;
+            }
+            f.write_char('\n')?;
+        }
+
+        let mut sources = report.sources().peekable();
+        if sources.peek().is_some() {
+            f.write_str("\n**Sources:**\n")?;
+        }
+        for (i, source) in sources.enumerate() {
+            let indent = write_list_marker(f, i + 1)?;
+            write!(MarkdownEscape::new(&mut *f, indent), "{source}")?;
+            f.write_char('\n')?;
+        }
+
+        if let Some(backtrace) = report.backtrace() {
+            write!(f, "\n**Stack backtrace:**\n```text\n{backtrace}\n```\n")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// An HTML fragment, with classes to style each part of the report. Every
+/// message is escaped.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HtmlRenderer;
+
+impl ReportRenderer for HtmlRenderer {
+    fn render(&self, f: &mut dyn fmt::Write, report: &ReportView<'_>) -> fmt::Result {
+        f.write_str("<div class=\"error-report\">\n<p class=\"root\">")?;
+        report.write_root(&mut HtmlEscape(&mut *f))?;
+        if let Some(location) = report.location() {
+            f.write_str(" ")?;
+            write_html_location(f, location)?;
+        }
+        f.write_str("</p>\n")?;
+
+        let mut frames = report.frames().peekable();
+        if frames.peek().is_some() {
+            f.write_str("<h4>Caused by</h4>\n<ol class=\"context\">\n")?;
+            for frame in frames {
+                f.write_str("<li>")?;
+                HtmlEscape(&mut *f).write_str(frame.context())?;
+                if !frame.fields().is_empty() {
+                    f.write_str(" <span class=\"fields\">")?;
+                    write_fields(&mut HtmlEscape(&mut *f), frame.fields())?;
+                    f.write_str("</span>")?;
+                }
+                if let Some(location) = report.frame_location(frame) {
+                    f.write_str(" ")?;
+                    write_html_location(f, location)?;
+                }
+                f.write_str("</li>\n")?;
+            }
+            f.write_str("</ol>\n")?;
+        }
+
+        let mut sources = report.sources().peekable();
+        if sources.peek().is_some() {
+            f.write_str("<h4>Sources</h4>\n<ol class=\"sources\">\n")?;
+            for source in sources {
+                f.write_str("<li>")?;
+                write!(HtmlEscape(&mut *f), "{source}")?;
+                f.write_str("</li>\n")?;
+            }
+            f.write_str("</ol>\n")?;
+        }
+
+        if let Some(backtrace) = report.backtrace() {
+            f.write_str("<h4>Stack backtrace</h4>\n<pre class=\"backtrace\">")?;
+            write!(HtmlEscape(&mut *f), "{backtrace}")?;
+            f.write_str("</pre>\n")?;
+        }
+
+        f.write_str("</div>\n")
+    }
+}
+
+/// Walks a double ended iterator from either end.
+struct InOrder<I> {
+    iter: I,
+    reverse: bool,
+}
+
+impl<I: DoubleEndedIterator> Iterator for InOrder<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.reverse {
+            true => self.iter.next_back(),
+            false => self.iter.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<I: DoubleEndedIterator + ExactSizeIterator> ExactSizeIterator for InOrder<I> {}
+
+fn write_html_location(f: &mut dyn fmt::Write, location: &Location<'_>) -> fmt::Result {
+    f.write_str("<span class=\"location\">")?;
+    write!(HtmlEscape(&mut *f), "{location}")?;
+    f.write_str("</span>")
+}
+
+/// Writes the marker of a numbered list item, returning its width.
+fn write_list_marker(f: &mut dyn fmt::Write, n: usize) -> Result<usize, fmt::Error> {
+    write!(f, "{n}. ")?;
+    Ok(n.ilog10() as usize + 3)
+}
+
+fn write_backticks(f: &mut dyn fmt::Write, n: usize) -> fmt::Result {
+    (0..n).try_for_each(|_| f.write_char('`'))
+}
+
+/// Escapes everything written through it for Markdown text. Lines after the
+/// first are indented to stay in their list item, and can't start a new block.
+struct MarkdownEscape<'a> {
+    out: &'a mut dyn fmt::Write,
+    indent: usize,
+    line: Line,
+}
+
+/// Where a [MarkdownEscape] is within its current line.
+#[derive(Clone, Copy, PartialEq)]
+enum Line {
+    /// Only whitespace so far, so a block marker could follow.
+    Start,
+    /// Only digits so far, so an ordered list marker could follow.
+    Digits,
+    Text,
+}
+
+impl<'a> MarkdownEscape<'a> {
+    fn new(out: &'a mut dyn fmt::Write, indent: usize) -> Self {
+        MarkdownEscape {
+            out,
+            indent,
+            line: Line::Text,
+        }
+    }
+}
+
+impl fmt::Write for MarkdownEscape<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            match c {
+                '\r' => continue,
+                '\n' => {
+                    self.out.write_char('\n')?;
+                    (0..self.indent).try_for_each(|_| self.out.write_char(' '))?;
+                    self.line = Line::Start;
+                    continue;
+                }
+                _ => {}
+            }
+            let escape = is_markdown_special(c)
+                || match self.line {
+                    Line::Start => matches!(c, '#' | '-' | '+' | '=' | '~' | '|'),
+                    Line::Digits => matches!(c, '.' | ')'),
+                    Line::Text => false,
+                };
+            self.line = match self.line {
+                Line::Start | Line::Digits if c.is_ascii_digit() => Line::Digits,
+                Line::Start if c == ' ' || c == '\t' => Line::Start,
+                _ => Line::Text,
+            };
+            if escape {
+                self.out.write_char('\\')?;
+            }
+            self.out.write_char(c)?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes the content of a code span on a single line, as a line break could
+/// start a new block. Code spans show line breaks as spaces anyway.
+struct CodeSpan<'a>(&'a mut dyn fmt::Write);
+
+impl fmt::Write for CodeSpan<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for part in s.split_inclusive(['\r', '\n']) {
+            match part.strip_suffix(['\r', '\n']) {
+                Some(text) => {
+                    self.0.write_str(text)?;
+                    self.0.write_char(' ')?;
+                }
+                None => self.0.write_str(part)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Measures the longest run of backticks written through it.
+#[derive(Default)]
+struct BacktickRuns {
+    current: usize,
+    longest: usize,
+}
+
+impl fmt::Write for BacktickRuns {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            self.current = if c == '`' { self.current + 1 } else { 0 };
+            self.longest = self.longest.max(self.current);
+        }
+        Ok(())
+    }
+}
+
+fn is_markdown_special(c: char) -> bool {
+    matches!(c, '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>')
+}
+
+/// Escapes everything written through it for HTML text and attributes.
+struct HtmlEscape<'a>(&'a mut dyn fmt::Write);
+
+impl fmt::Write for HtmlEscape<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for part in s.split_inclusive(['&', '<', '>', '"', '\'']) {
+            let (text, escaped) = match part.char_indices().last() {
+                Some((i, '&')) => (&part[..i], "&amp;"),
+                Some((i, '<')) => (&part[..i], "&lt;"),
+                Some((i, '>')) => (&part[..i], "&gt;"),
+                Some((i, '"')) => (&part[..i], "&quot;"),
+                Some((i, '\'')) => (&part[..i], "&#39;"),
+                _ => (part, ""),
+            };
+            self.0.write_str(text)?;
+            self.0.write_str(escaped)?;
+        }
+        Ok(())
+    }
+}
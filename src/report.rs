@@ -0,0 +1,154 @@
+//! The human readable report printed by the alternate `{:#?}` [Debug] format
+//! of [impl_context](crate::impl_context) wrappers.
+use crate::hook;
+use crate::render::{PlainRenderer, Rendered, ReportRenderer, ReportView};
+use crate::stack::Stack;
+use std::error::Error;
+use std::fmt::{self, Debug};
+use std::io;
+
+/// A configurable view of a context enriched error, formatted through [Debug].
+///
+/// The alternate `{:#?}` format of every wrapper prints a [Report] with the
+/// default settings. Use the wrapper's `report` method to change them, and
+/// [set_hook](crate::hook::set_hook) to change the layout of every report.
+///
+/// ** Example **
+/// ```
+/// use thiserror::Error;
+/// use thiserror_context::{Context, impl_context};
+///
+/// #[derive(Debug, Error)]
+/// enum ThisErrorInner {
+///     #[error("parse int err: {0}")]
+///     ParseInt(#[from] std::num::ParseIntError),
+/// }
+/// impl_context!(ThisError(ThisErrorInner));
+///
+/// fn parse(s: &str) -> Result<i64, ThisError> {
+///     s.parse::<i64>().context("parsing")
+/// }
+///
+/// let err = parse("fake").unwrap_err();
+/// assert_eq!(format!("{:?}", err.report().backtrace(false).locations(false)), r#"ParseInt(ParseIntError { kind: InvalidDigit })
+///
+/// Caused by:
+///     0: parsing
+///
+/// Sources:
+///     0: invalid digit found in string
+/// "#);
+///
+/// assert_eq!(format!("{:?}", err.report().backtrace(false).sources(false).locations(false)), r#"ParseInt(ParseIntError { kind: InvalidDigit })
+///
+/// Caused by:
+///     0: parsing
+/// "#);
+/// ```
+pub struct Report<'a, E> {
+    stack: &'a Stack<E>,
+    sources: bool,
+    backtrace: bool,
+    locations: bool,
+}
+
+impl<'a, E> Report<'a, E> {
+    #[doc(hidden)]
+    pub fn new(stack: &'a Stack<E>) -> Self {
+        Report {
+            stack,
+            sources: true,
+            backtrace: true,
+            locations: true,
+        }
+    }
+
+    /// Whether to list the [source](Error::source) chain of the root error
+    /// after the context frames. Enabled by default.
+    pub fn sources(mut self, sources: bool) -> Self {
+        self.sources = sources;
+        self
+    }
+
+    /// Whether to print the backtrace captured along with the root error, if
+    /// any was captured. Enabled by default.
+    pub fn backtrace(mut self, backtrace: bool) -> Self {
+        self.backtrace = backtrace;
+        self
+    }
+
+    /// Whether to print where the root error was created and where each
+    /// context was added. Enabled by default.
+    pub fn locations(mut self, locations: bool) -> Self {
+        self.locations = locations;
+        self
+    }
+}
+
+impl<'a, E: Error> Report<'a, E> {
+    /// What this report prints, for a [ReportRenderer].
+    pub fn view(&self) -> ReportView<'a> {
+        ReportView {
+            root: self.stack.root(),
+            location: self.stack.location(),
+            frames: self.stack.frames(),
+            backtrace: self.stack.backtrace(),
+            handler: hook::handler(),
+            sources: self.sources,
+            show_backtrace: self.backtrace,
+            locations: self.locations,
+        }
+    }
+
+    /// Formats this report with the given renderer through `Display`.
+    pub fn render_with<R: ReportRenderer + ?Sized>(&self, renderer: &'a R) -> Rendered<'a, R> {
+        Rendered::new(self.view(), renderer)
+    }
+}
+
+impl<E: Error> Report<'_, E> {
+    /// Writes this report, as printed by `{:#?}`, to a [fmt::Write].
+    ///
+    /// The frames are walked in place and written directly, so nothing is
+    /// allocated beyond what formatting the root error and its sources needs,
+    /// unless the backtrace is printed: resolving it allocates.
+    pub fn write_to(&self, w: &mut impl fmt::Write) -> fmt::Result {
+        PlainRenderer.render(w, &self.view())
+    }
+
+    /// Writes this report, as printed by `{:#?}`, to an [io::Write], without
+    /// allocating like [write_to](Self::write_to).
+    pub fn write_to_io(&self, w: &mut impl io::Write) -> io::Result<()> {
+        let mut adapter = IoAdapter {
+            inner: w,
+            error: None,
+        };
+        match self.write_to(&mut adapter) {
+            Ok(()) => Ok(()),
+            Err(fmt::Error) => Err(adapter
+                .error
+                .unwrap_or_else(|| io::Error::other("formatter error"))),
+        }
+    }
+}
+
+/// Writes formatted text to an [io::Write], keeping the first error.
+struct IoAdapter<'a, W: ?Sized> {
+    inner: &'a mut W,
+    error: Option<io::Error>,
+}
+
+impl<W: io::Write + ?Sized> fmt::Write for IoAdapter<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|e| {
+            self.error = Some(e);
+            fmt::Error
+        })
+    }
+}
+
+impl<E: Error> Debug for Report<'_, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        PlainRenderer.render(f, &self.view())
+    }
+}
@@ -0,0 +1,145 @@
+//! Serialization of [impl_context](crate::impl_context) wrappers, behind the
+//! `serde` feature.
+//!
+//! [Contextual] and every wrapper serialize as long as their root error does,
+//! and deserialize as long as their root error does, into the following
+//! structure:
+//!
+//! ```json
+//! {
+//!     "version": 1,
+//!     "error": <root>,
+//!     "display": "<root, as displayed>",
+//!     "context": ["outer", "inner"]
+//! }
+//! ```
+//!
+//! `display` is only informative and is ignored when deserializing. Locations,
+//! fields, attachments and the backtrace are not carried over, so a
+//! deserialized error only keeps its root and context messages, recorded where
+//! it was deserialized.
+//!
+//! ** Example **
+//! ```
+//! use serde::{Deserialize, Serialize};
+//! use thiserror::Error;
+//! use thiserror_context::{Context, impl_context};
+//!
+//! #[derive(Debug, Error, Serialize, Deserialize)]
+//! enum ThisErrorInner {
+//!     #[error("placeholder err")]
+//!     Placeholder,
+//! }
+//! impl_context!(ThisError(ThisErrorInner));
+//!
+//! let err = Err::<(), _>(ThisErrorInner::Placeholder)
+//!     .context("inner")
+//!     .context("outer")
+//!     .unwrap_err();
+//!
+//! let json = serde_json::to_string(&err).unwrap();
+//! assert_eq!(
+//!     json,
+//!     r#"{"version":1,"error":"Placeholder","display":"placeholder err","context":["outer","inner"]}"#
+//! );
+//!
+//! let err: ThisError = serde_json::from_str(&json).unwrap();
+//! assert_eq!(err.contexts().collect::<Vec<_>>(), ["outer", "inner"]);
+//! ```
+use crate::contextual::Contextual;
+use serde::de::{self, Deserialize, Deserializer};
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::fmt::Display;
+
+#[doc(hidden)]
+pub use serde as __serde;
+
+/// The version of the serialized structure. Deserializing a newer version is
+/// an error.
+pub const VERSION: u32 = 1;
+
+struct Displayed<'a, E>(&'a E);
+
+impl<E: Display> Serialize for Displayed<'_, E> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self.0)
+    }
+}
+
+struct Contexts<'a, E>(&'a Contextual<E>);
+
+impl<E> Serialize for Contexts<'_, E> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.contexts())
+    }
+}
+
+impl<E: Serialize + Display> Serialize for Contextual<E> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("Error", 4)?;
+        s.serialize_field("version", &VERSION)?;
+        s.serialize_field("error", self.root())?;
+        s.serialize_field("display", &Displayed(self.root()))?;
+        s.serialize_field("context", &Contexts(self))?;
+        s.end()
+    }
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename = "Error")]
+struct Repr<E> {
+    version: u32,
+    error: E,
+    #[serde(default)]
+    context: Vec<String>,
+}
+
+impl<'de, E: Deserialize<'de>> Deserialize<'de> for Contextual<E> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let repr = Repr::<E>::deserialize(deserializer)?;
+        if repr.version > VERSION {
+            return Err(de::Error::custom(format_args!(
+                "unsupported error format version {}, expected at most {}",
+                repr.version, VERSION
+            )));
+        }
+        Ok(Contextual::from_parts(repr.error, repr.context))
+    }
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __impl_serde {
+    ($out:ident[$($gen:tt)*]($ty:ty)[$($bounds:tt)*]) => {
+        // The higher-ranked bounds keep these impls from failing to compile
+        // when the root error doesn't implement the serde traits.
+        impl<$($gen)*> $crate::serialization::__serde::Serialize for $out<$($gen)*>
+        where
+            for<'__a> $ty: $crate::serialization::__serde::Serialize + ::std::fmt::Display,
+            $($bounds)*
+        {
+            fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+            where
+                S: $crate::serialization::__serde::Serializer,
+            {
+                $crate::serialization::__serde::Serialize::serialize(&self.0, serializer)
+            }
+        }
+
+        impl<'__de, $($gen)*> $crate::serialization::__serde::Deserialize<'__de> for $out<$($gen)*>
+        where
+            for<'__a> $ty: $crate::serialization::__serde::Deserialize<'__de>,
+            $($bounds)*
+        {
+            fn deserialize<D>(deserializer: D) -> ::std::result::Result<Self, D::Error>
+            where
+                D: $crate::serialization::__serde::Deserializer<'__de>,
+            {
+                <$crate::Contextual<$ty> as $crate::serialization::__serde::Deserialize>::deserialize(
+                    deserializer,
+                )
+                .map($out)
+            }
+        }
+    };
+}
@@ -0,0 +1,209 @@
+//! The storage behind every [impl_context](crate::impl_context) wrapper.
+//!
+//! The root error sits next to a single list of context frames, stored
+//! innermost first, so adding context is an amortized push and walking the
+//! frames never recurses.
+use crate::attachments::Attachments;
+use crate::chain;
+use crate::fields::{Field, Value};
+use crate::frame::Frame;
+use std::backtrace::Backtrace;
+use std::error::Error;
+use std::panic::Location;
+
+pub struct Stack<E> {
+    root: E,
+    frames: Frames,
+    backtrace: Option<Box<Backtrace>>,
+    location: &'static Location<'static>,
+}
+
+/// The context frames of a [Stack], along with the attachments of its root.
+///
+/// It doesn't depend on the root error type, so iterators over it don't
+/// borrow from the root's generic parameters.
+#[derive(Default)]
+pub struct Frames {
+    // Innermost first.
+    frames: Vec<Frame>,
+    attachments: Attachments,
+}
+
+impl Frames {
+    /// The context frames, outermost first.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &Frame> + ExactSizeIterator {
+        self.frames.iter().rev()
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// The context messages, outermost first.
+    pub fn contexts(&self) -> impl DoubleEndedIterator<Item = &str> + ExactSizeIterator {
+        self.iter().map(Frame::context)
+    }
+
+    pub fn fields(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.iter()
+            .flat_map(|frame| frame.fields().iter().map(|(k, v)| (k.as_ref(), v)))
+    }
+
+    pub fn request_all<A: 'static>(&self) -> impl Iterator<Item = &A> {
+        self.iter()
+            .map(Frame::attachments)
+            .chain([&self.attachments])
+            .flat_map(Attachments::iter)
+    }
+}
+
+impl<E> Stack<E> {
+    /// Wraps a root error, capturing a backtrace and the caller's location.
+    #[track_caller]
+    pub fn new(root: E) -> Self {
+        Stack::with_backtrace(root, crate::backtrace::capture(), Location::caller())
+    }
+
+    fn with_backtrace(
+        root: E,
+        backtrace: Option<Box<Backtrace>>,
+        location: &'static Location<'static>,
+    ) -> Self {
+        Stack {
+            root,
+            frames: Frames::default(),
+            backtrace,
+            location,
+        }
+    }
+
+    pub fn root(&self) -> &E {
+        &self.root
+    }
+
+    pub fn into_root(self) -> E {
+        self.root
+    }
+
+    /// Wraps a root error along with context messages, outermost first, all
+    /// recorded at the caller's location.
+    #[track_caller]
+    pub fn from_parts(root: E, contexts: Vec<String>) -> Self {
+        let mut stack = Stack::new(root);
+        let location = Location::caller();
+        stack.frames.frames = contexts
+            .into_iter()
+            .rev()
+            .map(|context| Frame::new(context, location, Vec::new()))
+            .collect();
+        stack
+    }
+
+    /// The root error and the context messages, outermost first.
+    pub fn into_parts(self) -> (E, Vec<String>) {
+        let contexts = self
+            .frames
+            .frames
+            .into_iter()
+            .rev()
+            .map(Frame::into_context)
+            .collect();
+        (self.root, contexts)
+    }
+
+    /// Replaces the root error, keeping the frames, backtrace, location and
+    /// attachments.
+    pub fn map_root<U>(self, f: impl FnOnce(E, &'static Location<'static>) -> U) -> Stack<U> {
+        let location = self.location;
+        let (root, rest) = self.take_root();
+        rest.with_root(f(root, location))
+    }
+
+    /// Replaces the root error if `f` succeeds, or puts back the root it
+    /// returns otherwise.
+    pub fn try_map_root<U>(self, f: impl FnOnce(E) -> Result<U, E>) -> Result<Stack<U>, Self> {
+        let (root, rest) = self.take_root();
+        match f(root) {
+            Ok(root) => Ok(rest.with_root(root)),
+            Err(root) => Err(rest.with_root(root)),
+        }
+    }
+
+    /// Takes the root error out, along with everything needed to build a
+    /// stack around another one.
+    fn take_root(self) -> (E, Rest) {
+        let rest = Rest {
+            frames: self.frames,
+            backtrace: self.backtrace,
+            location: self.location,
+        };
+        (self.root, rest)
+    }
+
+    /// Wraps a root error that was already part of another stack, which
+    /// keeps the backtrace.
+    pub fn nested(root: E, location: &'static Location<'static>) -> Self {
+        Stack::with_backtrace(root, None, location)
+    }
+
+    pub fn push(
+        &mut self,
+        context: String,
+        location: &'static Location<'static>,
+        fields: Vec<Field>,
+    ) {
+        self.frames
+            .frames
+            .push(Frame::new(context, location, fields));
+    }
+
+    pub fn attach<A: Send + Sync + 'static>(&mut self, attachment: A) {
+        match self.frames.frames.last_mut() {
+            Some(frame) => frame.attachments_mut().push(attachment),
+            None => self.frames.attachments.push(attachment),
+        }
+    }
+
+    pub fn frames(&self) -> &Frames {
+        &self.frames
+    }
+
+    pub fn backtrace(&self) -> &Backtrace {
+        crate::backtrace::or_disabled(&self.backtrace)
+    }
+
+    pub fn location(&self) -> &'static Location<'static> {
+        self.location
+    }
+}
+
+/// A [Stack] whose root error was taken out.
+struct Rest {
+    frames: Frames,
+    backtrace: Option<Box<Backtrace>>,
+    location: &'static Location<'static>,
+}
+
+impl Rest {
+    fn with_root<E>(self, root: E) -> Stack<E> {
+        Stack {
+            frames: self.frames,
+            ..Stack::with_backtrace(root, self.backtrace, self.location)
+        }
+    }
+}
+
+impl<E: Error + 'static> Stack<E> {
+    /// The outermost context frame, or the root's own source if no context
+    /// was added.
+    pub fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match chain::outermost(&self.frames.frames, &self.root) {
+            Some(frame) => Some(frame),
+            None => self.root.source(),
+        }
+    }
+}
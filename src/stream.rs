@@ -0,0 +1,202 @@
+//! Context for every failing item of a stream of `Result`s, behind the
+//! `futures` feature.
+//!
+//! [TryStreamContextExt] is the stream counterpart of
+//! [IteratorContextExt](crate::IteratorContextExt), for streams such as the
+//! rows returned by sqlx's `fetch`.
+//!
+//! ** Example **
+//! ```
+//! use futures_util::{stream, TryStreamExt};
+//! use thiserror::Error;
+//! use thiserror_context::{impl_context, TryStreamContextExt};
+//!
+//! #[derive(Debug, Error)]
+//! enum ThisErrorInner {
+//!     #[error("bad row")]
+//!     BadRow,
+//! }
+//! impl_context!(ThisError(ThisErrorInner));
+//!
+//! # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+//! let rows = stream::iter([Ok(1), Err(ThisErrorInner::BadRow)]);
+//! let err: ThisError = rows
+//!     .with_context(|idx| format!("reading row {idx}"))
+//!     .try_collect::<Vec<i64>>()
+//!     .await
+//!     .unwrap_err();
+//!
+//! assert_eq!(err.contexts().collect::<Vec<_>>(), ["reading row 1"]);
+//! # });
+//! ```
+use crate::contextual::Wrapper;
+use crate::iter::Position;
+use futures_core::Stream;
+use std::fmt::Display;
+use std::pin::Pin;
+use std::task::{ready, Poll};
+
+/// Adds context to every error of a stream of `Result`s.
+pub trait TryStreamContextExt<T, E>: Stream<Item = Result<T, E>> + Sized {
+    /// Wrap every error with the same additional context.
+    #[track_caller]
+    fn context<W, C, U>(self, context: C) -> ContextStream<Self, C, W>
+    where
+        E: Into<W>,
+        W: Wrapper<U>,
+        C: Display;
+
+    /// Wrap every error with additional context, evaluated lazily from the
+    /// position of the failing item.
+    #[track_caller]
+    fn with_context<W, C, F, U>(self, f: F) -> WithContextStream<Self, F, W>
+    where
+        E: Into<W>,
+        W: Wrapper<U>,
+        C: Display,
+        F: FnMut(usize) -> C;
+}
+
+impl<S, T, E> TryStreamContextExt<T, E> for S
+where
+    S: Stream<Item = Result<T, E>>,
+{
+    #[track_caller]
+    fn context<W, C, U>(self, context: C) -> ContextStream<Self, C, W>
+    where
+        E: Into<W>,
+        W: Wrapper<U>,
+        C: Display,
+    {
+        ContextStream {
+            stream: self,
+            context,
+            position: Position::new(),
+        }
+    }
+
+    #[track_caller]
+    fn with_context<W, C, F, U>(self, f: F) -> WithContextStream<Self, F, W>
+    where
+        E: Into<W>,
+        W: Wrapper<U>,
+        C: Display,
+        F: FnMut(usize) -> C,
+    {
+        WithContextStream {
+            stream: self,
+            f,
+            position: Position::new(),
+        }
+    }
+}
+
+pin_project_lite::pin_project! {
+    /// A stream adding the same context to every error, returned by
+    /// [TryStreamContextExt::context].
+    #[derive(Debug)]
+    pub struct ContextStream<S, C, W> {
+        #[pin]
+        stream: S,
+        context: C,
+        position: Position<W>,
+    }
+}
+
+impl<S: Clone, C: Clone, W> Clone for ContextStream<S, C, W> {
+    fn clone(&self) -> Self {
+        ContextStream {
+            stream: self.stream.clone(),
+            context: self.context.clone(),
+            position: self.position,
+        }
+    }
+}
+
+impl<S, C, W> ContextStream<S, C, W> {
+    /// Also record the position of each failing item as an `index` field.
+    pub fn with_index(self) -> Self {
+        ContextStream {
+            position: self.position.with_index(),
+            ..self
+        }
+    }
+}
+
+impl<S, T, E, C, W> Stream for ContextStream<S, C, W>
+where
+    S: Stream<Item = Result<T, E>>,
+    E: Into<W>,
+    C: Display,
+{
+    type Item = Result<T, W>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let item = ready!(this.stream.poll_next(cx));
+        let context = &*this.context;
+        Poll::Ready(item.map(|item| this.position.record(item, |_| context)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.stream.size_hint()
+    }
+}
+
+pin_project_lite::pin_project! {
+    /// A stream adding lazily evaluated context to every error, returned by
+    /// [TryStreamContextExt::with_context].
+    #[derive(Debug)]
+    pub struct WithContextStream<S, F, W> {
+        #[pin]
+        stream: S,
+        f: F,
+        position: Position<W>,
+    }
+}
+
+impl<S: Clone, F: Clone, W> Clone for WithContextStream<S, F, W> {
+    fn clone(&self) -> Self {
+        WithContextStream {
+            stream: self.stream.clone(),
+            f: self.f.clone(),
+            position: self.position,
+        }
+    }
+}
+
+impl<S, F, W> WithContextStream<S, F, W> {
+    /// Also record the position of each failing item as an `index` field.
+    pub fn with_index(self) -> Self {
+        WithContextStream {
+            position: self.position.with_index(),
+            ..self
+        }
+    }
+}
+
+impl<S, T, E, C, F, W> Stream for WithContextStream<S, F, W>
+where
+    S: Stream<Item = Result<T, E>>,
+    E: Into<W>,
+    C: Display,
+    F: FnMut(usize) -> C,
+{
+    type Item = Result<T, W>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let item = ready!(this.stream.poll_next(cx));
+        Poll::Ready(item.map(|item| this.position.record(item, this.f)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.stream.size_hint()
+    }
+}
@@ -0,0 +1,79 @@
+//! Counts allocations made by the current thread, so it needs its own binary
+//! to install a global allocator.
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use thiserror::Error;
+use thiserror_context::{impl_context, Context};
+
+struct Counting;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+#[derive(Debug, Error)]
+pub enum ThisErrorInner {
+    #[error("parse int err")]
+    ParseInt(#[from] std::num::ParseIntError),
+}
+impl_context!(ThisError(ThisErrorInner));
+
+/// Turns backtrace capture on. std reads the variable once, on the first
+/// capture, so every test calls this before creating an error.
+fn capture_backtraces() {
+    std::env::set_var("RUST_LIB_BACKTRACE", "1");
+}
+
+#[test]
+fn write_report_does_not_allocate() {
+    use std::backtrace::BacktraceStatus;
+
+    // With a backtrace captured, leaving it out is what keeps this from
+    // allocating.
+    capture_backtraces();
+    let r: Result<i64, ThisError> = "x".parse::<i64>().context("inner");
+    let err = r.context("outer").unwrap_err();
+    assert_eq!(err.backtrace().status(), BacktraceStatus::Captured);
+    let mut buf = [0u8; 1024];
+    let mut s = String::with_capacity(1024);
+
+    let before = ALLOCATIONS.with(Cell::get);
+    err.write_report(&mut &mut buf[..]).unwrap();
+    err.write_report_fmt(&mut s).unwrap();
+    assert_eq!(ALLOCATIONS.with(Cell::get), before);
+
+    // Formatting into a new string does allocate, so the count is live.
+    assert_eq!(s, format!("{:?}", err.report().backtrace(false)));
+    assert!(ALLOCATIONS.with(Cell::get) > before);
+}
+
+#[test]
+fn source_chain_does_not_allocate() {
+    use std::error::Error;
+
+    capture_backtraces();
+    let mut r: Result<i64, ThisError> = "x".parse::<i64>().context("0");
+    for i in 1..100 {
+        r = r.context(i);
+    }
+    let err = r.unwrap_err();
+
+    let before = ALLOCATIONS.with(Cell::get);
+    let sources = std::iter::successors(err.source(), |&e| e.source()).count();
+    assert_eq!(ALLOCATIONS.with(Cell::get), before);
+    assert_eq!(sources, 102);
+}
@@ -0,0 +1,99 @@
+use std::error::Error as _;
+use thiserror::Error;
+use thiserror_context::ErrorContext;
+
+mod users {
+    use super::*;
+
+    /// Errors of the users module.
+    #[derive(Debug, Error, ErrorContext)]
+    #[context(wrapper = "UserError", vis = "pub(crate)", attr(must_use))]
+    pub enum UserErrorInner {
+        #[error("user not found")]
+        NotFound,
+        #[error(transparent)]
+        Parse(#[from] std::num::ParseIntError),
+    }
+
+    pub(crate) fn load(id: &str) -> Result<(), UserError> {
+        // `Context` is not in scope here, so call it through its path.
+        let id: i64 = thiserror_context::Context::context(id.parse::<i64>(), "parsing id")?;
+        thiserror_context::Context::with_context(Err(UserErrorInner::NotFound), || {
+            format!("loading user {id}")
+        })
+    }
+}
+
+#[test]
+fn derived_wrapper_behaves_like_impl_context() {
+    let err = users::load("1").unwrap_err();
+    assert!(matches!(err.root(), users::UserErrorInner::NotFound));
+    assert_eq!(err.contexts().collect::<Vec<_>>(), ["loading user 1"]);
+    assert_eq!(err.to_string(), "user not found");
+    assert_eq!(err.source().unwrap().to_string(), "loading user 1");
+
+    let err = users::load("one").unwrap_err();
+    assert!(matches!(err.root(), users::UserErrorInner::Parse(_)));
+    assert_eq!(
+        format!(
+            "{:?}",
+            err.report()
+                .backtrace(false)
+                .locations(false)
+                .sources(false)
+        ),
+        "Parse(ParseIntError { kind: InvalidDigit })\n\nCaused by:\n    0: parsing id\n"
+    );
+}
+
+mod renamed {
+    use super::*;
+
+    mod reexport {
+        pub use thiserror_context::*;
+    }
+
+    #[derive(Debug, Error, ErrorContext)]
+    #[context(
+        wrapper = "RenamedError",
+        doc = "Reached through a re-export.",
+        crate = "reexport"
+    )]
+    pub enum RenamedErrorInner {
+        #[error("renamed")]
+        Renamed,
+    }
+
+    #[test]
+    fn crate_path_can_be_overridden() {
+        let err: RenamedError = RenamedErrorInner::Renamed.into();
+        assert_eq!(err.context_len(), 0);
+    }
+}
+
+mod generic {
+    use super::*;
+    use std::fmt::Debug;
+
+    #[derive(Debug, Error, ErrorContext)]
+    #[context(wrapper = "StoreError")]
+    pub enum StoreErrorInner<'a, K: Debug>
+    where
+        K: 'static,
+    {
+        #[error("key not found: {0:?}")]
+        NotFound(&'a K),
+    }
+
+    #[test]
+    fn generics_are_forwarded() {
+        fn get(key: &u32) -> Result<(), StoreError<'_, u32>> {
+            thiserror_context::Context::context(Err(StoreErrorInner::NotFound(key)), "getting")
+        }
+
+        let err = get(&1).unwrap_err();
+        assert!(matches!(err.root(), StoreErrorInner::NotFound(1)));
+        assert_eq!(err.contexts().collect::<Vec<_>>(), ["getting"]);
+        assert_eq!(err.to_string(), "key not found: 1");
+    }
+}
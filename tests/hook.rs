@@ -0,0 +1,52 @@
+//! The report hook is process-wide, so it is tested in its own binary.
+use std::fmt;
+use thiserror::Error;
+use thiserror_context::hook::{self, HookAlreadySet, Order, ReportHandler, RootFormat};
+use thiserror_context::{impl_context, Context};
+
+#[derive(Debug, Error)]
+pub enum ThisErrorInner {
+    #[error("parse int err")]
+    ParseInt(#[from] std::num::ParseIntError),
+}
+impl_context!(ThisError(ThisErrorInner));
+
+struct Numbered;
+
+impl ReportHandler for Numbered {
+    fn order(&self) -> Order {
+        Order::InnermostFirst
+    }
+
+    fn indent(&self) -> &str {
+        "\t"
+    }
+
+    fn number(&self, f: &mut dyn fmt::Write, index: usize) -> fmt::Result {
+        write!(f, "#{}. ", index + 1)
+    }
+
+    fn root_format(&self) -> RootFormat {
+        RootFormat::Display
+    }
+}
+
+#[test]
+fn hook_controls_every_report() {
+    let r: Result<i64, ThisError> = "x".parse::<i64>().context("inner");
+    let err = r.context("outer").unwrap_err();
+
+    hook::set_hook(Numbered).unwrap();
+    assert_eq!(hook::set_hook(Numbered), Err(HookAlreadySet));
+
+    assert_eq!(
+        format!("{:?}", err.report().backtrace(false).locations(false)),
+        "parse int err\n\nCaused by:\n\t#1. inner\n\t#2. outer\n\nSources:\n\t#1. invalid digit found in string\n"
+    );
+    assert_eq!(format!("{err:#?}"), format!("{:?}", err.report()),);
+    // The compact format isn't affected.
+    assert_eq!(
+        format!("{err:?}"),
+        r#"ThisError { root: ParseInt(ParseIntError { kind: InvalidDigit }), context: ["outer", "inner"] }"#
+    );
+}